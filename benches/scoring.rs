@@ -30,7 +30,7 @@ fn criterion_benchmark(c: &mut Criterion) {
     let games = replay
         .moves
         .iter()
-        .map(|(request, _response)| logic::Game::from(request))
+        .map(|(request, _response)| logic::Game::try_from(request).unwrap())
         .collect::<Vec<logic::Game>>();
 
     let count = games.len();
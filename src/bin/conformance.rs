@@ -0,0 +1,47 @@
+use std::{fs::File, io::stdin};
+
+use topsnek::util::{conformance, gamelogger};
+
+fn main() {
+    let files: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut games = vec![];
+    if files.is_empty() {
+        games.push(("<stdin>".to_string(), gamelogger::Game::load(&mut stdin())));
+    }
+    for filename in files {
+        let game = match File::open(&filename) {
+            Ok(mut f) => gamelogger::Game::load(&mut f),
+            Err(e) => Err(e.to_string()),
+        };
+        games.push((filename, game));
+    }
+
+    let mut failed = false;
+    for (filename, game) in games {
+        let game = match game {
+            Ok(g) => g,
+            Err(e) => {
+                println!("{}: failed to load game: {}", filename, e);
+                failed = true;
+                continue;
+            }
+        };
+
+        let mismatches = conformance::check_game(&game);
+        println!(
+            "{}: {} turns, {} mismatches",
+            filename,
+            game.moves.len(),
+            mismatches.len()
+        );
+        for m in mismatches.iter() {
+            println!("{}", m);
+        }
+        failed |= !mismatches.is_empty();
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -1,20 +1,24 @@
-use topsnek::{logic::scoring::winter, snakes, util::gauntlet::Gauntlet};
+use std::time::Duration;
+
+use topsnek::{
+    logic::scoring::winter,
+    snakes,
+    util::gauntlet::{GameSettings, Gauntlet},
+};
 
 const CHAMPIONS: &str = include_str!("champions.txt");
 
 const SNAKE_COUNT: usize = 42;
 
 fn main() {
-    let mut g = Gauntlet::new(&[
-        "--shrinkEveryNTurns",
-        "20",
-        "-g",
-        "wrapped",
-        "--map",
-        "royale",
-        "-t",
-        "750",
-    ]);
+    let mut settings = GameSettings {
+        map: "royale".to_string(),
+        timeout: Duration::from_millis(750),
+        ..GameSettings::default()
+    };
+    settings.ruleset.name = "wrapped".to_string();
+    settings.ruleset.settings.royale.shrink_every_n_turns = 20;
+    let mut g = Gauntlet::new(settings);
 
     let ref_snake_count = snakes::snakes().len();
 
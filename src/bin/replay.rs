@@ -5,20 +5,51 @@ use std::fs::File;
 
 use topsnek::{util::gamelogger, *};
 
+const DEFAULT_TREE_DEPTH: usize = 3;
+const DEFAULT_TREE_WIDTH: usize = 8;
+
+fn usage(cmd_name: &str) -> ! {
+    println!(
+        "usage: {} [--tree <turn> [--tree-format dot|json] [--tree-depth <turns>] [--tree-width <children>]] <snakename> [<start_turn> <end_turn> <millis_per_move>]",
+        cmd_name
+    );
+    std::process::exit(1);
+}
+
 fn main() {
     let snakes = snakes::snakes();
-    let mut args = std::env::args();
+    let mut all_args = std::env::args();
+    let cmd_name = all_args.next().unwrap_or("replay".into());
+
+    // Options go first, the rest are positional arguments
+    let mut all_args = all_args.peekable();
+    let mut tree_dump = None;
+    let (mut format, mut depth, mut width) = (
+        snakes::TreeFormat::Dot,
+        DEFAULT_TREE_DEPTH,
+        DEFAULT_TREE_WIDTH,
+    );
+    while let Some(option) = all_args.next_if(|a| a.starts_with("--")) {
+        let value = all_args.next().unwrap_or_else(|| usage(&cmd_name));
+        match option.as_str() {
+            "--tree" => tree_dump = Some(value.parse().unwrap()),
+            "--tree-format" => format = value.parse().unwrap(),
+            "--tree-depth" => depth = value.parse().unwrap(),
+            "--tree-width" => width = value.parse().unwrap(),
+            _ => usage(&cmd_name),
+        }
+    }
+    let tree_dump = tree_dump.map(|turn| gamelogger::TreeDump {
+        turn,
+        format,
+        depth,
+        width,
+    });
 
-    let cmd_name = args.next().unwrap_or("replay".into());
+    let mut args = all_args;
     let snake_name = match args.next() {
         Some(v) => v,
-        None => {
-            println!(
-                "usage: {} <snakename> [<start_turn> <end_turn> <millis_per_move>]",
-                cmd_name
-            );
-            std::process::exit(1);
-        }
+        None => usage(&cmd_name),
     };
 
     let start_turn: Option<usize> = args.next().map(|f| f.parse().unwrap());
@@ -41,7 +72,13 @@ fn main() {
                 .build()
                 .unwrap();
 
-            game.replay(snake.as_ref(), start_turn, end_turn, millis);
+            game.replay(
+                snake.as_ref(),
+                start_turn,
+                end_turn,
+                millis,
+                tree_dump.as_ref(),
+            );
 
             #[cfg(feature = "profiling")]
             {
@@ -43,6 +43,14 @@ fn main() {
                 }
             },
 
+            (GET) (/{id: String}/report/{game_id: String}) => {
+                // The search report of the last move in a game, for debugging
+                match snakes.get(&id).and_then(|snake| snake.last_report(&game_id)) {
+                    Some(report) => rouille::Response::json(&report),
+                    None => rouille::Response::empty_404(),
+                }
+            },
+
             (POST) (/{id: String}/start) => {
                 println!("starting new game for: '{}'", id);
                 match snakes.get(&id) {
@@ -109,17 +117,24 @@ fn main() {
                                     Ok(response) => {
                                         {
                                             let mut gamelogger = gamelogger.lock().unwrap();
-                                            gamelogger.log_move(&request_body, Some(&response));
+                                            let report = snake.last_report(&request_body.game.id);
+                                            gamelogger.log_move(&request_body, Some(&response), report);
+                                        }
+                                        if let Ok(game) = logic::Game::try_from(&request_body) {
+                                            println!("{}", game);
                                         }
-                                        println!("{}\n{} {}", logic::Game::from(&request_body), response.direction, response.shout);
+                                        println!("{} {}", response.direction, response.shout);
                                         rouille::Response::json(&response)
                                     },
                                     Err(msg) => {
                                         {
                                             let mut gamelogger = gamelogger.lock().unwrap();
-                                            gamelogger.log_move(&request_body, None);
+                                            gamelogger.log_move(&request_body, None, None);
+                                        }
+                                        if let Ok(game) = logic::Game::try_from(&request_body) {
+                                            println!("{}", game);
                                         }
-                                        println!("{}\nERROR {}", logic::Game::from(&request_body), msg);
+                                        println!("ERROR {}", msg);
                                         rouille::Response::text(msg).with_status_code(500)
                                     },
                                 };
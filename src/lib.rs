@@ -8,4 +8,20 @@ pub trait Battlesnake {
     fn start(&self, req: &protocol::Request) -> Result<(), String>;
     fn end(&self, req: &protocol::Request) -> Result<(), String>;
     fn make_move(&self, req: &protocol::Request) -> Result<protocol::MoveResponse, String>;
+    /// last_report returns the report of the last search for a game, for
+    /// snakes that keep one.
+    fn last_report(&self, _game_id: &str) -> Option<snakes::SearchReport> {
+        None
+    }
+    /// export_tree returns the search tree of a game, up to depth turns deep
+    /// and with at most width children per node, for snakes that keep one.
+    fn export_tree(
+        &self,
+        _game_id: &str,
+        _format: snakes::TreeFormat,
+        _depth: usize,
+        _width: usize,
+    ) -> Option<String> {
+        None
+    }
 }
@@ -0,0 +1,443 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+
+use super::{game::GameMode, Direction, Game, Point, Snake};
+
+// Boards up to 640 tiles, like the 25x25 board, fit in the bitboard itself
+// and don't need an allocation.
+const INLINE_WORDS: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Words {
+    // The number of words in use, and the words
+    Inline(u8, [u64; INLINE_WORDS]),
+    Heap(Vec<u64>),
+}
+
+impl Words {
+    fn new(count: usize) -> Self {
+        if count <= INLINE_WORDS {
+            Words::Inline(count as u8, [0; INLINE_WORDS])
+        } else {
+            Words::Heap(vec![0; count])
+        }
+    }
+
+    #[inline(always)]
+    fn as_slice(&self) -> &[u64] {
+        match self {
+            Words::Inline(count, w) => &w[..*count as usize],
+            Words::Heap(w) => w,
+        }
+    }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [u64] {
+        match self {
+            Words::Inline(count, w) => &mut w[..*count as usize],
+            Words::Heap(w) => w,
+        }
+    }
+}
+
+/// Bitboard is a set of tiles, stored as one bit per tile in the same order
+/// as the tiles of a Board: x + y * width.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bitboard {
+    width: u8,
+    height: u8,
+    words: Words,
+}
+
+impl Default for Bitboard {
+    fn default() -> Self {
+        Bitboard::new(0, 0)
+    }
+}
+
+impl Bitboard {
+    pub fn new(width: isize, height: isize) -> Self {
+        let tiles = (width * height) as usize;
+        Bitboard {
+            width: width as u8,
+            height: height as u8,
+            words: Words::new(tiles.div_ceil(64)),
+        }
+    }
+
+    /// full returns a bitboard with every tile set.
+    pub fn full(width: isize, height: isize) -> Self {
+        let mut b = Bitboard::new(width, height);
+        b.words.as_mut_slice().fill(u64::MAX);
+        b.clear_padding();
+        b
+    }
+
+    #[inline(always)]
+    pub fn width(&self) -> isize {
+        self.width as isize
+    }
+
+    #[inline(always)]
+    pub fn height(&self) -> isize {
+        self.height as isize
+    }
+
+    #[inline(always)]
+    fn tiles(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    #[inline(always)]
+    fn index(&self, p: &Point) -> Option<usize> {
+        if p.out_of_bounds(self.width(), self.height()) {
+            None
+        } else {
+            Some(p.x as usize + p.y as usize * self.width as usize)
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self, p: &Point) -> bool {
+        match self.index(p) {
+            Some(idx) => self.get_index(idx),
+            None => false,
+        }
+    }
+
+    #[inline(always)]
+    pub fn set(&mut self, p: &Point, value: bool) {
+        if let Some(idx) = self.index(p) {
+            self.set_index(idx, value);
+        }
+    }
+
+    #[inline(always)]
+    pub(super) fn get_index(&self, idx: usize) -> bool {
+        self.words.as_slice()[idx / 64] & 1 << (idx % 64) != 0
+    }
+
+    #[inline(always)]
+    pub(super) fn set_index(&mut self, idx: usize, value: bool) {
+        let word = &mut self.words.as_mut_slice()[idx / 64];
+        if value {
+            *word |= 1 << (idx % 64);
+        } else {
+            *word &= !(1 << (idx % 64));
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.words
+            .as_slice()
+            .iter()
+            .map(|w| w.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.as_slice().iter().all(|w| *w == 0)
+    }
+
+    /// points lists the tiles in the set, from the bottom row to the top.
+    pub fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        let w = self.width as usize;
+        self.words
+            .as_slice()
+            .iter()
+            .enumerate()
+            .flat_map(|(i, word)| {
+                let mut word = *word;
+                std::iter::from_fn(move || {
+                    if word == 0 {
+                        return None;
+                    }
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    Some(i * 64 + bit)
+                })
+            })
+            .map(move |idx| Point {
+                x: (idx % w) as i8,
+                y: (idx / w) as i8,
+            })
+    }
+
+    /// and_not removes the tiles of other from the set.
+    pub fn and_not(&mut self, other: &Bitboard) {
+        for (a, b) in self
+            .words
+            .as_mut_slice()
+            .iter_mut()
+            .zip(other.words.as_slice())
+        {
+            *a &= !b;
+        }
+    }
+
+    // Bits past the last tile are always zero
+    fn clear_padding(&mut self) {
+        let tiles = self.tiles();
+        for (i, word) in self.words.as_mut_slice().iter_mut().enumerate() {
+            let start = i * 64;
+            if start >= tiles {
+                *word = 0;
+            } else if tiles - start < 64 {
+                *word &= (1 << (tiles - start)) - 1;
+            }
+        }
+    }
+
+    // Moves every tile n places up in the tile order
+    fn shl(&self, n: usize) -> Bitboard {
+        let mut res = Bitboard::new(self.width(), self.height());
+        let (src, dst) = (self.words.as_slice(), res.words.as_mut_slice());
+        let (words, bits) = (n / 64, n % 64);
+        for i in (words..dst.len()).rev() {
+            dst[i] = src[i - words] << bits;
+            if bits > 0 && i > words {
+                dst[i] |= src[i - words - 1] >> (64 - bits);
+            }
+        }
+        res.clear_padding();
+        res
+    }
+
+    // Moves every tile n places down in the tile order
+    fn shr(&self, n: usize) -> Bitboard {
+        let mut res = Bitboard::new(self.width(), self.height());
+        let (src, dst) = (self.words.as_slice(), res.words.as_mut_slice());
+        let (words, bits) = (n / 64, n % 64);
+        for i in 0..dst.len().saturating_sub(words) {
+            dst[i] = src[i + words] >> bits;
+            if bits > 0 && i + words + 1 < src.len() {
+                dst[i] |= src[i + words + 1] << (64 - bits);
+            }
+        }
+        res
+    }
+}
+
+impl BitAndAssign<&Bitboard> for Bitboard {
+    fn bitand_assign(&mut self, other: &Bitboard) {
+        for (a, b) in self
+            .words
+            .as_mut_slice()
+            .iter_mut()
+            .zip(other.words.as_slice())
+        {
+            *a &= b;
+        }
+    }
+}
+
+impl BitOrAssign<&Bitboard> for Bitboard {
+    fn bitor_assign(&mut self, other: &Bitboard) {
+        for (a, b) in self
+            .words
+            .as_mut_slice()
+            .iter_mut()
+            .zip(other.words.as_slice())
+        {
+            *a |= b;
+        }
+    }
+}
+
+impl BitAnd for &Bitboard {
+    type Output = Bitboard;
+
+    fn bitand(self, other: &Bitboard) -> Bitboard {
+        let mut res = self.clone();
+        res &= other;
+        res
+    }
+}
+
+impl BitOr for &Bitboard {
+    type Output = Bitboard;
+
+    fn bitor(self, other: &Bitboard) -> Bitboard {
+        let mut res = self.clone();
+        res |= other;
+        res
+    }
+}
+
+impl Not for &Bitboard {
+    type Output = Bitboard;
+
+    fn not(self) -> Bitboard {
+        let mut res = self.clone();
+        for w in res.words.as_mut_slice() {
+            *w = !*w;
+        }
+        res.clear_padding();
+        res
+    }
+}
+
+/// Grid moves bitboards around on a board of a given size, so the
+/// neighbours of many tiles are found in a few word operations.
+pub struct Grid {
+    width: isize,
+    height: isize,
+    wrapped: bool,
+    // The leftmost and rightmost column of the board
+    left: Bitboard,
+    right: Bitboard,
+}
+
+impl Grid {
+    pub fn new(width: isize, height: isize, wrapped: bool) -> Self {
+        let mut left = Bitboard::new(width, height);
+        let mut right = Bitboard::new(width, height);
+        for y in 0..height as i8 {
+            left.set(&Point { x: 0, y }, true);
+            right.set(
+                &Point {
+                    x: width as i8 - 1,
+                    y,
+                },
+                true,
+            );
+        }
+        Grid {
+            width,
+            height,
+            wrapped,
+            left,
+            right,
+        }
+    }
+
+    /// for_game returns the grid of the board of the game, which wraps
+    /// around in wrapped mode.
+    pub fn for_game(game: &Game) -> Self {
+        Grid::new(
+            game.board.width(),
+            game.board.height(),
+            game.rules.game_mode == GameMode::Wrapped,
+        )
+    }
+
+    /// shift moves every tile one step in the given direction. Tiles that
+    /// leave the board are dropped, or come back on the other side when the
+    /// board wraps.
+    pub fn shift(&self, b: &Bitboard, dir: Direction) -> Bitboard {
+        let (w, h) = (self.width as usize, self.height as usize);
+        match dir {
+            Direction::Up => {
+                let mut res = b.shl(w);
+                if self.wrapped {
+                    res |= &b.shr(w * (h - 1));
+                }
+                res
+            }
+            Direction::Down => {
+                let mut res = b.shr(w);
+                if self.wrapped {
+                    res |= &b.shl(w * (h - 1));
+                }
+                res
+            }
+            Direction::Right => {
+                let mut res = b.shl(1);
+                res.and_not(&self.left);
+                if self.wrapped {
+                    res |= &(&b.shr(w - 1) & &self.left);
+                }
+                res
+            }
+            Direction::Left => {
+                let mut res = b.shr(1);
+                res.and_not(&self.right);
+                if self.wrapped {
+                    res |= &(&b.shl(w - 1) & &self.right);
+                }
+                res
+            }
+        }
+    }
+
+    /// expand adds the neighbours of every tile to the set.
+    pub fn expand(&self, b: &Bitboard) -> Bitboard {
+        let mut res = b.clone();
+        for dir in crate::protocol::ALL_DIRECTIONS {
+            res |= &self.shift(b, dir);
+        }
+        res
+    }
+
+    /// reachable returns the tiles that can be reached from the given tiles
+    /// in at most max_steps steps, moving only through passable tiles.
+    pub fn reachable(&self, from: &Bitboard, passable: &Bitboard, max_steps: usize) -> Bitboard {
+        let mut res = from.clone();
+        let mut frontier = from.clone();
+        for _ in 0..max_steps {
+            frontier = &self.expand(&frontier) & passable;
+            frontier.and_not(&res);
+            if frontier.is_empty() {
+                break;
+            }
+            res |= &frontier;
+        }
+        res
+    }
+}
+
+/// Bitboards keeps a bitboard for every kind of tile, next to the bytes of a
+/// Board. Board updates them on every change.
+#[derive(Clone, Debug, Default)]
+pub struct Bitboards {
+    // Snake bodies, including heads
+    pub snakes: Bitboard,
+    pub heads: Bitboard,
+    pub food: Bitboard,
+    // Tiles with at least one hazard
+    pub hazards: Bitboard,
+    pub walls: Bitboard,
+}
+
+impl Bitboards {
+    pub fn new(width: isize, height: isize) -> Self {
+        Bitboards {
+            snakes: Bitboard::new(width, height),
+            heads: Bitboard::new(width, height),
+            food: Bitboard::new(width, height),
+            hazards: Bitboard::new(width, height),
+            walls: Bitboard::new(width, height),
+        }
+    }
+}
+
+/// blocked returns the tiles that are certain death to move onto next
+/// turn: walls and snake bodies, except for tails that move out of the way.
+pub fn blocked(game: &Game) -> Bitboard {
+    let bits = game.board.bits();
+    let mut res = &bits.snakes | &bits.walls;
+    for snake in std::iter::once(&game.you).chain(game.others.iter()) {
+        let len = snake.body.len();
+        if snake.dead() || len < 2 || snake.body[len - 1] == snake.body[len - 2] {
+            continue;
+        }
+        res.set(&snake.body[len - 1], false);
+    }
+    res
+}
+
+/// safe_moves lists the moves of a snake that don't end on a blocked tile.
+pub fn safe_moves(game: &Game, snake: &Snake, blocked: &Bitboard) -> Vec<Direction> {
+    snake
+        .head
+        .neighbours()
+        .into_iter()
+        .filter_map(|(dir, mut p)| {
+            game.warp(&mut p);
+            if p.out_of_bounds(game.board.width(), game.board.height()) || blocked.get(&p) {
+                None
+            } else {
+                Some(dir)
+            }
+        })
+        .collect()
+}
@@ -1,24 +1,47 @@
-use crate::{
-    log,
-    protocol::{self, Point},
-};
+use std::cmp;
 
-use super::Tile;
+use crate::protocol::{self, Point};
 
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+use super::{bitboard::Bitboards, zobrist, Tile};
+
+#[derive(Clone, Default, Debug)]
 pub struct Board {
     pub(super) data: Vec<u8>,
+    // Zobrist hash of all tiles, updated on every change
+    hash: u64,
+    // Bitboards of all tiles, updated on every change
+    bits: Bitboards,
+    // Previous values of every changed tile, while a move is being made
+    journal: Option<Vec<(u16, u8)>>,
+}
+
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl Eq for Board {}
+
+impl std::hash::Hash for Board {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash)
+    }
 }
 
+// Every tile is a single byte: the low 3 bits hold the tile type, the other
+// 5 bits count the hazards stacked on the tile.
 const TILE_MASK: u8 = 0b11111111;
-const HAZARD_MASK: u8 = 0b11111100;
-const TILE_TYPE_MASK: u8 = 0b00000011;
-const EMPTY: u8 = 0b00;
-const SNAKE: u8 = 0b01;
-const HEAD: u8 = 0b10;
-const FOOD: u8 = 0b11;
+const HAZARD_MASK: u8 = 0b11111000;
+const HAZARD_SHIFT: u8 = 3;
+const TILE_TYPE_MASK: u8 = 0b00000111;
+const EMPTY: u8 = 0b000;
+const SNAKE: u8 = 0b001;
+const HEAD: u8 = 0b010;
+const FOOD: u8 = 0b011;
+const WALL: u8 = 0b100;
 
-pub const MAX_HAZARDS: u8 = HAZARD_MASK >> 2;
+pub const MAX_HAZARDS: u8 = HAZARD_MASK >> HAZARD_SHIFT;
 
 macro_rules! get_tile {
     ($data:expr, $x:expr, $y:expr, $mask:expr) => {{
@@ -30,23 +53,77 @@ macro_rules! get_tile {
 }
 
 macro_rules! set_tile {
-    ($data:expr, $x:expr, $y:expr, $mask:expr, $value:expr) => {{
-        let w = $data[0] as usize;
+    ($board:expr, $x:expr, $y:expr, $mask:expr, $value:expr) => {{
+        let w = $board.data[0] as usize;
         let (x, y) = ($x as usize, $y as usize);
         let idx = 2 + x + y * w;
-        let v = unsafe { $data.get_unchecked_mut(idx) };
+        let v = unsafe { $board.data.get_unchecked_mut(idx) };
+        if let Some(journal) = $board.journal.as_mut() {
+            journal.push((idx as u16, *v));
+        }
+        let old = *v;
         *v = (!$mask & *v | $mask & $value);
+        $board.hash ^= zobrist::tile_key(idx, old) ^ zobrist::tile_key(idx, *v);
+        update_bits(&mut $board.bits, idx - 2, *v);
     }};
 }
 
+/// MAX_BOARD_DIMENSION is the largest supported width and height, points
+/// store their coordinates in an i8.
+pub const MAX_BOARD_DIMENSION: usize = i8::MAX as usize;
+
 impl Board {
     pub fn new(w: usize, h: usize) -> Board {
+        assert!(
+            w <= MAX_BOARD_DIMENSION && h <= MAX_BOARD_DIMENSION,
+            "unsupported board size {}x{}",
+            w,
+            h
+        );
         let count = w * h + 2;
         let mut data = Vec::with_capacity(count);
         data.resize(count, 0 as u8);
         data[0] = w as u8;
         data[1] = h as u8;
-        Board { data }
+        Board {
+            data,
+            hash: 0,
+            bits: Bitboards::new(w as isize, h as isize),
+            journal: None,
+        }
+    }
+
+    /// zobrist returns the hash of the food, hazards, walls and snake tiles
+    /// on the board.
+    #[inline(always)]
+    pub fn zobrist(&self) -> u64 {
+        self.hash
+    }
+
+    /// start_journal starts recording every tile change, so they can be
+    /// reverted with undo().
+    pub(super) fn start_journal(&mut self) {
+        self.journal = Some(vec![]);
+    }
+
+    pub(super) fn take_journal(&mut self) -> Vec<(u16, u8)> {
+        self.journal.take().unwrap_or_default()
+    }
+
+    /// undo reverts the changes recorded in a journal.
+    pub(super) fn undo(&mut self, journal: &[(u16, u8)]) {
+        for (idx, v) in journal.iter().rev() {
+            let idx = *idx as usize;
+            self.hash ^= zobrist::tile_key(idx, self.data[idx]) ^ zobrist::tile_key(idx, *v);
+            self.data[idx] = *v;
+            update_bits(&mut self.bits, idx - 2, *v);
+        }
+    }
+
+    /// bits returns the bitboards of the snakes, food, hazards and walls.
+    #[inline(always)]
+    pub fn bits(&self) -> &Bitboards {
+        &self.bits
     }
 
     #[inline(always)]
@@ -77,12 +154,17 @@ impl Board {
         self.check_type(p, EMPTY)
     }
 
+    #[inline(always)]
+    pub fn is_wall(&self, p: &Point) -> bool {
+        self.check_type(p, WALL)
+    }
+
     #[inline(always)]
     pub fn hazard_count(&self, p: &Point) -> u8 {
         if p.x < 0 || p.y < 0 || p.x as isize >= self.width() || p.y as isize >= self.height() {
             return 0;
         }
-        get_tile!(self.data, p.x, p.y, HAZARD_MASK) >> 2
+        get_tile!(self.data, p.x, p.y, HAZARD_MASK) >> HAZARD_SHIFT
     }
 
     pub fn set(&mut self, p: &Point, t: Tile) {
@@ -90,24 +172,21 @@ impl Board {
             return;
         }
         match t {
-            Tile::Empty => set_tile!(self.data, p.x, p.y, TILE_MASK, EMPTY),
-            Tile::Snake => set_tile!(self.data, p.x, p.y, TILE_MASK, SNAKE),
-            Tile::Head => set_tile!(self.data, p.x, p.y, TILE_MASK, HEAD),
-            Tile::Food => set_tile!(self.data, p.x, p.y, TILE_MASK, FOOD),
-            Tile::Hazard(x) => set_tile!(self.data, p.x, p.y, TILE_MASK, (x as u8) << 2),
+            Tile::Empty => set_tile!(self, p.x, p.y, TILE_MASK, EMPTY),
+            Tile::Snake => set_tile!(self, p.x, p.y, TILE_MASK, SNAKE),
+            Tile::Head => set_tile!(self, p.x, p.y, TILE_MASK, HEAD),
+            Tile::Food => set_tile!(self, p.x, p.y, TILE_MASK, FOOD),
+            Tile::Hazard(x) => set_tile!(self, p.x, p.y, TILE_MASK, hazard_bits(x)),
             Tile::HazardWithFood(x) => {
-                set_tile!(self.data, p.x, p.y, TILE_MASK, ((x as u8) << 2) | FOOD)
+                set_tile!(self, p.x, p.y, TILE_MASK, hazard_bits(x) | FOOD)
             }
             Tile::HazardWithSnake(x) => {
-                set_tile!(self.data, p.x, p.y, TILE_MASK, ((x as u8) << 2) | SNAKE)
+                set_tile!(self, p.x, p.y, TILE_MASK, hazard_bits(x) | SNAKE)
             }
             Tile::HazardWithHead(x) => {
-                set_tile!(self.data, p.x, p.y, TILE_MASK, ((x as u8) << 2) | HEAD)
-            }
-            Tile::Wall => {
-                log!("warning: attempt to configure a tile as a wall, this should never happen. Adding max hazards instead.");
-                set_tile!(self.data, p.x, p.y, TILE_MASK, HAZARD_MASK);
+                set_tile!(self, p.x, p.y, TILE_MASK, hazard_bits(x) | HEAD)
             }
+            Tile::Wall => set_tile!(self, p.x, p.y, TILE_MASK, WALL),
         }
     }
 
@@ -115,16 +194,9 @@ impl Board {
     pub fn damage(&self, p: &Point, hazard_dmg: i8) -> i8 {
         let t = get_tile!(self.data, p.x, p.y, TILE_MASK);
         match t & TILE_TYPE_MASK {
-            EMPTY => {
-                let hazards = (t & HAZARD_MASK) as u8 >> 2;
-                if hazards == MAX_HAZARDS {
-                    i8::MAX
-                } else {
-                    hazard_dmg * hazards as i8
-                }
-            }
+            EMPTY => hazard_dmg.saturating_mul((t >> HAZARD_SHIFT) as i8),
             FOOD => 0,
-            SNAKE | HEAD => i8::MAX,
+            SNAKE | HEAD | WALL => i8::MAX,
             _ => unreachable!(),
         }
     }
@@ -134,8 +206,10 @@ impl Board {
             return Tile::Wall;
         }
         let value = get_tile!(self.data, p.x, p.y, TILE_MASK);
-        let hazards = (value & HAZARD_MASK) as u8 >> 2;
-        if hazards > 0 {
+        let hazards = value >> HAZARD_SHIFT;
+        if value & TILE_TYPE_MASK == WALL {
+            Tile::Wall
+        } else if hazards > 0 {
             match value & TILE_TYPE_MASK {
                 EMPTY => Tile::Hazard(hazards),
                 HEAD => Tile::HazardWithHead(hazards),
@@ -161,55 +235,47 @@ impl Board {
         } else {
             hazard_count -= count
         }
-        set_tile!(self.data, p.x, p.y, HAZARD_MASK, hazard_count << 2);
+        set_tile!(self, p.x, p.y, HAZARD_MASK, hazard_count << HAZARD_SHIFT);
     }
 
     pub fn add(&mut self, p: &Point, t: Tile) {
         if p.x < 0 || p.y < 0 || p.x as isize >= self.width() || p.y as isize >= self.height() {
             return;
         }
+        if self.is_wall(p) {
+            // Nothing can be placed on a wall
+            return;
+        }
+        let hazards = |x: u8| hazard_bits(self.hazard_count(p).saturating_add(x));
         match t {
-            Tile::Empty => set_tile!(self.data, p.x, p.y, TILE_TYPE_MASK, EMPTY),
-            Tile::Snake => set_tile!(self.data, p.x, p.y, TILE_TYPE_MASK, SNAKE),
-            Tile::Head => set_tile!(self.data, p.x, p.y, TILE_TYPE_MASK, HEAD),
-            Tile::Food => set_tile!(self.data, p.x, p.y, TILE_TYPE_MASK, FOOD),
+            Tile::Empty => set_tile!(self, p.x, p.y, TILE_TYPE_MASK, EMPTY),
+            Tile::Snake => set_tile!(self, p.x, p.y, TILE_TYPE_MASK, SNAKE),
+            Tile::Head => set_tile!(self, p.x, p.y, TILE_TYPE_MASK, HEAD),
+            Tile::Food => set_tile!(self, p.x, p.y, TILE_TYPE_MASK, FOOD),
             Tile::Hazard(x) => {
-                let mut hazard_count = self.hazard_count(p) + x as u8;
-                if hazard_count > MAX_HAZARDS {
-                    hazard_count = MAX_HAZARDS;
-                }
-                set_tile!(self.data, p.x, p.y, HAZARD_MASK, hazard_count << 2);
+                let v = hazards(x);
+                set_tile!(self, p.x, p.y, HAZARD_MASK, v);
             }
             Tile::HazardWithFood(x) => {
-                let mut hazard_count = self.hazard_count(p) + x as u8;
-                if hazard_count > MAX_HAZARDS {
-                    hazard_count = MAX_HAZARDS;
-                }
-                set_tile!(self.data, p.x, p.y, TILE_MASK, (hazard_count << 2) | FOOD)
+                let v = hazards(x);
+                set_tile!(self, p.x, p.y, TILE_MASK, v | FOOD)
             }
             Tile::HazardWithSnake(x) => {
-                let mut hazard_count = self.hazard_count(p) + x as u8;
-                if hazard_count > MAX_HAZARDS {
-                    hazard_count = MAX_HAZARDS;
-                }
-                set_tile!(self.data, p.x, p.y, TILE_MASK, (hazard_count << 2) | SNAKE)
+                let v = hazards(x);
+                set_tile!(self, p.x, p.y, TILE_MASK, v | SNAKE)
             }
             Tile::HazardWithHead(x) => {
-                let mut hazard_count = self.hazard_count(p) + x as u8;
-                if hazard_count > MAX_HAZARDS {
-                    hazard_count = MAX_HAZARDS;
-                }
-                set_tile!(self.data, p.x, p.y, TILE_MASK, (hazard_count << 2) | HEAD)
-            }
-            Tile::Wall => {
-                println!("warning: attempt to configure a tile as a wall, this should never happen. Adding max hazards instead.");
-                set_tile!(self.data, p.x, p.y, HAZARD_MASK, HAZARD_MASK);
+                let v = hazards(x);
+                set_tile!(self, p.x, p.y, TILE_MASK, v | HEAD)
             }
+            Tile::Wall => set_tile!(self, p.x, p.y, TILE_MASK, WALL),
         }
     }
 
     pub fn clear_snake(&mut self, p: &Point) {
-        self.add(p, Tile::Empty);
+        if self.is_snake(p) || self.is_head(p) {
+            self.add(p, Tile::Empty);
+        }
     }
 
     #[inline(always)]
@@ -223,6 +289,22 @@ impl Board {
     }
 }
 
+#[inline(always)]
+fn hazard_bits(count: u8) -> u8 {
+    cmp::min(count, MAX_HAZARDS) << HAZARD_SHIFT
+}
+
+#[inline(always)]
+fn update_bits(bits: &mut Bitboards, idx: usize, value: u8) {
+    let tile_type = value & TILE_TYPE_MASK;
+    bits.snakes
+        .set_index(idx, tile_type == SNAKE || tile_type == HEAD);
+    bits.heads.set_index(idx, tile_type == HEAD);
+    bits.food.set_index(idx, tile_type == FOOD);
+    bits.walls.set_index(idx, tile_type == WALL);
+    bits.hazards.set_index(idx, value & HAZARD_MASK != 0);
+}
+
 impl From<&protocol::Board> for Board {
     fn from(g: &protocol::Board) -> Self {
         let mut b = Board::new(g.width, g.height);
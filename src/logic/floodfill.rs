@@ -1,198 +1,94 @@
-use std::collections::HashMap;
+use std::{cmp::Reverse, collections::HashMap};
 
-use crate::{
-    logic::{game::GameMode, Point},
-    util::stackqueue::StackDequeue,
+use super::{
+    bitboard::{Bitboard, Grid},
+    Game, Snake,
 };
 
-use super::{Game, Snake};
-
 type NumType = u16;
-const MAX_SNAKES: usize = 32;
-const MAX_BOARD_DIMENSION: usize = 25;
-const NO_SNAKE: NumType = NumType::MAX;
-
-#[derive(Default, Copy, Clone)]
-struct VoronoiTile {
-    x: i8,
-    y: i8,
-    snake: NumType,
-    distance: NumType,
-    length: NumType,
-}
 
-fn stack_voronoi(game: &Game, max_distance: NumType) -> [usize; MAX_SNAKES] {
-    let warp = game.rules.game_mode == GameMode::Wrapped;
-
-    let mut queue: StackDequeue<VoronoiTile, 256> = StackDequeue::new();
-    let mut scores: [usize; MAX_SNAKES] = [0; MAX_SNAKES];
-    let mut board = [VoronoiTile {
-        x: 0,
-        y: 0,
-        snake: NO_SNAKE,
-        distance: NumType::MAX,
-        length: 0,
-    }; MAX_BOARD_DIMENSION * MAX_BOARD_DIMENSION];
+/// voronoi calls f with the number of tiles claimed by every snake. Every
+/// step, all snakes claim the free neighbours of the tiles they claimed in
+/// the previous step. A tile reached by several snakes in the same step goes
+/// to the longest of them, or to nobody if that's a draw.
+fn voronoi<R>(game: &Game, max_distance: NumType, f: impl FnOnce(&[usize]) -> R) -> R {
+    let grid = Grid::for_game(game);
+    let bits = game.board.bits();
     let (w, h) = (game.board.width(), game.board.height());
 
-    fn tile_index(p: &Point) -> usize {
-        p.x as usize + p.y as usize * MAX_BOARD_DIMENSION
-    }
-
-    queue.push_back(VoronoiTile {
-        x: game.you.head.x,
-        y: game.you.head.y,
-        snake: 0,
-        distance: 0,
-        length: game.you.length as NumType,
-    });
-    for (i, snake) in game.others.iter().enumerate() {
-        queue.push_back(VoronoiTile {
-            x: snake.head.x,
-            y: snake.head.y,
-            snake: i as NumType + 1,
-            distance: 0,
-            length: snake.length as NumType,
-        });
-    }
-
-    while let Some(work) = queue.pop_front() {
-        let tile_idx = tile_index(&Point {
-            x: work.x,
-            y: work.y,
-        });
-
-        if board[tile_idx].snake != NO_SNAKE
-            && board[tile_idx].snake != work.snake
-            && board[tile_idx].distance == work.distance
-            && board[tile_idx].length == work.length
-        {
-            // Draw: no snake gets this tile, unmark the current snake & decrease its score
-            #[cfg(test)]
-            println!(
-                "Removing snake #{} from {},{}: draw with snake #{}",
-                board[tile_idx].snake, work.x, work.y, work.snake
-            );
-            let snake = board[tile_idx].snake;
-            board[tile_idx].snake = NO_SNAKE;
-            scores[snake as usize] -= 1;
-        } else if board[tile_idx].distance > work.distance
-            || (board[tile_idx].distance == work.distance && board[tile_idx].length < work.length)
-        {
-            if board[tile_idx].snake != NO_SNAKE {
-                let snake = board[tile_idx].snake;
-                #[cfg(test)]
-                println!(
-                    "Removing snake #{} from {},{}: beaten by snake #{}",
-                    snake, work.x, work.y, work.snake
-                );
-                scores[snake as usize] -= 1;
-            }
-
-            // We're first!
-            #[cfg(test)]
-            println!(
-                "Snake #{} claims {},{}: distance {}",
-                work.snake, work.x, work.y, work.distance
-            );
-            board[tile_idx] = work;
-            scores[work.snake as usize] += 1;
-
-            // Enqueue neighbouring tiles
-            let next_distance = work.distance + 1;
-            let p = Point {
-                x: work.x,
-                y: work.y,
-            };
-            for (_dir, mut next_p) in p.neighbours() {
-                if warp {
-                    next_p.warp(w, h);
-                }
-                // This next check does not allow traversing survivable hazards, unless hey have food.
-                if next_p.out_of_bounds(w, h)
-                    || game.board.hazard_count(&next_p) > 0
-                    || game.board.is_snake(&next_p)
-                    || game.board.is_head(&next_p)
-                {
-                    #[cfg(test)]
-                    {
-                        if next_p.out_of_bounds(w, h) {
-                            println!(
-                                "Not queueing {} for snake #{}: out of bounds",
-                                next_p, work.snake
-                            );
-                        }
-                        if game.board.hazard_count(&next_p) > 0 {
-                            println!("Not queueing {} for snake #{}: hazard", next_p, work.snake);
-                        }
-                        if game.board.is_snake(&next_p) || game.board.is_head(&next_p) {
-                            println!(
-                                "Not queueing {} for snake #{}: collision: {}",
-                                next_p,
-                                work.snake,
-                                game.board.get(&next_p)
-                            );
-                        }
-                    }
-                    continue;
-                }
-
-                let next_idx = tile_index(&next_p);
-                if next_distance <= board[next_idx].distance && next_distance < max_distance {
-                    #[cfg(test)]
-                    println!("Queueing {} for snake #{}", next_p, work.snake);
-                    queue.push_back(VoronoiTile {
-                        x: next_p.x,
-                        y: next_p.y,
-                        snake: work.snake,
-                        distance: next_distance,
-                        length: work.length,
-                    })
-                }
+    // Survivable hazards can't be traversed either
+    let mut passable = &bits.snakes | &bits.walls;
+    passable |= &bits.hazards;
+    let passable = !&passable;
+
+    let snakes: Vec<&Snake> = std::iter::once(&game.you)
+        .chain(game.others.iter())
+        .collect();
+    let mut by_length: Vec<usize> = (0..snakes.len()).collect();
+    by_length.sort_by_key(|i| Reverse(snakes[*i].length));
+
+    let mut scores = vec![0; snakes.len()];
+    let mut claimed = Bitboard::new(w, h);
+    let mut frontiers: Vec<Bitboard> = snakes
+        .iter()
+        .map(|s| {
+            let mut b = Bitboard::new(w, h);
+            b.set(&s.head, true);
+            b
+        })
+        .collect();
+
+    let mut distance = 0;
+    loop {
+        if distance > 0 {
+            for frontier in frontiers.iter_mut() {
+                *frontier = &grid.expand(frontier) & &passable;
+                frontier.and_not(&claimed);
             }
         }
-    }
 
-    #[cfg(test)]
-    {
-        for s in 0..(game.others.len() + 1) {
-            println!("Snake #{}: {} tiles", s, scores[s]);
+        // Snakes of equal length draw on the tiles they reach together,
+        // shorter snakes lose the tiles reached by longer ones
+        for group in by_length.chunk_by(|a, b| snakes[*a].length == snakes[*b].length) {
+            let mut once = Bitboard::new(w, h);
+            let mut twice = Bitboard::new(w, h);
+            for i in group {
+                let frontier = &mut frontiers[*i];
+                frontier.and_not(&claimed);
+                twice |= &(&once & frontier);
+                once |= frontier;
+            }
+            for i in group {
+                frontiers[*i].and_not(&twice);
+                scores[*i] += frontiers[*i].count();
+            }
+            claimed |= &once;
         }
 
-        print!("   ");
-        for x in 0..game.board.width() {
-            print!("{:3}  ", x);
-        }
-        println!();
-        for y in 0..game.board.height() as i8 {
-            print!("{:2} ", y);
-            for x in 0..game.board.width() as i8 {
-                let idx = tile_index(&Point { x, y });
-                print!("[{:3}]", board[idx].snake)
-            }
-            println!();
+        distance += 1;
+        if distance >= max_distance || frontiers.iter().all(|f| f.is_empty()) {
+            break;
         }
     }
 
-    scores
+    f(&scores)
 }
 
 pub fn all<'a>(game: &'a Game) -> HashMap<&'a Snake, usize> {
-    let mut res = HashMap::new();
-
-    let scores = stack_voronoi(game, NumType::MAX);
-    res.insert(&game.you, scores[0]);
-    for (i, snake) in game.others.iter().enumerate() {
-        res.insert(&snake, scores[i + 1]);
-    }
-
-    res
+    voronoi(game, NumType::MAX, |scores| {
+        let mut res = HashMap::new();
+        res.insert(&game.you, scores[0]);
+        for (i, snake) in game.others.iter().enumerate() {
+            res.insert(snake, scores[i + 1]);
+        }
+        res
+    })
 }
 
 pub fn me(game: &Game) -> usize {
-    stack_voronoi(game, NumType::MAX)[0]
+    voronoi(game, NumType::MAX, |scores| scores[0])
 }
 
 pub fn me_range_limit(game: &Game, max_distance: NumType) -> usize {
-    stack_voronoi(game, max_distance)[0]
+    voronoi(game, max_distance, |scores| scores[0])
 }
@@ -1,24 +1,106 @@
-use std::hash::Hash;
+use std::{cmp, collections::HashMap, hash::Hash, sync::Arc};
 
-use super::{Board, Direction, Point, Snake, Tile};
-use crate::protocol;
+use rand::Rng;
 
-#[derive(Clone, Eq, PartialEq, Hash)]
+use super::{
+    board::MAX_BOARD_DIMENSION,
+    maps::MapKind,
+    request::Metadata,
+    royale::{self, SafeZone},
+    zobrist, Board, Direction, Point, Snake, Tile,
+};
+use crate::{log, protocol};
+
+/// MAX_SNAKES is the largest supported number of snakes in a game, snakes
+/// are numbered with a u8 and u8::MAX is used to mark tiles without a snake.
+pub const MAX_SNAKES: usize = u8::MAX as usize;
+
+#[derive(Clone, Eq, PartialEq)]
 pub struct Game {
     pub board: Board,
     pub others: Vec<Snake>,
+    // Snakes eliminated since the game was created from a request, including you
     pub dead_snakes: usize,
     pub you: Snake,
     pub timeout: std::time::Duration,
     pub rules: Rules,
     pub turn: usize,
-    pub snail_mode: bool,
+    pub map: MapKind,
+    // Everything from the request that isn't needed to play the game
+    pub metadata: Arc<Metadata>,
+}
+
+/// Undo holds the state changed by Game::make_move.
+pub struct Undo {
+    turn: usize,
+    dead_snakes: usize,
+    // You first, then the other snakes in the order before the move
+    snakes: Vec<SnakeUndo>,
+    // Snakes eliminated by the move, with their index in Game::others
+    removed: Vec<(usize, Snake)>,
+    board: Vec<(u16, u8)>,
+}
+
+struct SnakeUndo {
+    hash: u64,
+    head: Point,
+    health: i8,
+    length: usize,
+    body_len: usize,
+    // The tail that moved, if the body did not grow
+    tail: Option<Point>,
+}
+
+impl SnakeUndo {
+    fn new(snake: &Snake) -> Self {
+        Self {
+            hash: snake.hash,
+            head: snake.head,
+            health: snake.health,
+            length: snake.length,
+            body_len: snake.body.len(),
+            tail: None,
+        }
+    }
+
+    fn restore(&self, snake: &mut Snake) {
+        // Drop the new head and any tail segments added by eating or sharing length
+        snake.body.pop_front();
+        match self.tail {
+            Some(tail) => {
+                snake.body.truncate(self.body_len - 1);
+                snake.body.push_back(tail);
+            }
+            None => snake.body.truncate(self.body_len),
+        }
+        snake.hash = self.hash;
+        snake.head = self.head;
+        snake.health = self.health;
+        snake.length = self.length;
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Hash)]
 pub struct Rules {
     pub game_mode: GameMode,
     pub hazard_damage_per_turn: i8,
+    pub food_spawn_chance: u8,
+    pub minimum_food: u8,
+    // How often royale hazards grow from the edges of the board or hazard
+    // pits fill up, zero when hazards don't change.
+    pub shrink_every_n_turns: usize,
+    pub squad: SquadRules,
+    // Food is only spawned when the seed is known, otherwise the board is
+    // assumed to never receive new food.
+    pub seed: Option<u64>,
+}
+
+#[derive(Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct SquadRules {
+    pub allow_body_collisions: bool,
+    pub shared_elimination: bool,
+    pub shared_health: bool,
+    pub shared_length: bool,
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Hash)]
@@ -28,60 +110,381 @@ pub enum GameMode {
     Wrapped,
     Royale,
     Solo,
+    Squad,
+}
+
+impl GameMode {
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        match name {
+            "standard" => Some(GameMode::Standard),
+            "royale" => Some(GameMode::Royale),
+            "wrapped" => Some(GameMode::Wrapped),
+            "constrictor" => Some(GameMode::Constrictor),
+            "solo" => Some(GameMode::Solo),
+            "squad" => Some(GameMode::Squad),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Standard => "standard",
+            GameMode::Royale => "royale",
+            GameMode::Wrapped => "wrapped",
+            GameMode::Constrictor => "constrictor",
+            GameMode::Solo => "solo",
+            GameMode::Squad => "squad",
+        }
+    }
+}
+
+/// map_kind picks the map for a game mode and map name from a request.
+pub fn map_kind(game_mode: GameMode, name: &str) -> Option<MapKind> {
+    match MapKind::from_name(name) {
+        _ if game_mode == GameMode::Royale => Some(MapKind::Royale),
+        map => map,
+    }
 }
 
 impl Game {
+    /// validate rejects requests for games that are too large to play.
+    pub fn validate(req: &protocol::Request) -> Result<(), String> {
+        let (w, h) = (req.board.width, req.board.height);
+        if w == 0 || h == 0 || w > MAX_BOARD_DIMENSION || h > MAX_BOARD_DIMENSION {
+            return Err(format!(
+                "unsupported board size {}x{}, boards can be up to {}x{}",
+                w, h, MAX_BOARD_DIMENSION, MAX_BOARD_DIMENSION
+            ));
+        }
+        let snakes = req.board.snakes.len().max(1);
+        if snakes > MAX_SNAKES {
+            return Err(format!(
+                "unsupported number of snakes {}, games can have up to {}",
+                snakes, MAX_SNAKES
+            ));
+        }
+        Ok(())
+    }
+
     pub fn warp(&self, p: &mut Point) {
         if self.rules.game_mode == GameMode::Wrapped {
             p.warp(self.board.width(), self.board.height())
         }
     }
 
-    // Current implementation does not take into account:
-    // - moving or expanding hazards
-    // - spawning food
-    pub fn execute_moves(&mut self, you: Direction, others: &Vec<Direction>) {
-        let mut new_board = self.board.clone();
+    pub fn execute_moves(&mut self, you: Direction, others: &[Direction]) {
+        self.apply_moves(you, others, None)
+    }
 
-        if self.snail_mode {
-            for x in 0..new_board.width() {
-                for y in 0..new_board.height() {
-                    let p = Point {
-                        x: x as i8,
-                        y: y as i8,
-                    };
-                    new_board.remove_hazards(&p, 1)
-                }
-            }
-        }
+    /// make_move executes the moves like execute_moves, and returns the
+    /// changes needed to restore the current state with unmake_move.
+    pub fn make_move(&mut self, you: Direction, others: &[Direction]) -> Undo {
+        let mut undo = Undo {
+            turn: self.turn,
+            dead_snakes: self.dead_snakes,
+            snakes: Vec::with_capacity(others.len() + 1),
+            removed: vec![],
+            board: vec![],
+        };
+        self.board.start_journal();
+        self.apply_moves(you, others, Some(&mut undo));
+        undo.board = self.board.take_journal();
+        undo
+    }
 
-        self.you
-            .apply_move(you, &mut new_board, &self.rules, self.snail_mode);
-        for i in 0..others.len() {
-            self.others[i].apply_move(others[i], &mut new_board, &self.rules, self.snail_mode)
+    /// unmake_move reverts the last move made by make_move.
+    pub fn unmake_move(&mut self, undo: Undo) {
+        self.board.undo(&undo.board);
+        for (idx, snake) in undo.removed.into_iter().rev() {
+            self.others.insert(idx, snake);
         }
+        for (snake, snake_undo) in std::iter::once(&mut self.you)
+            .chain(self.others.iter_mut())
+            .zip(undo.snakes.iter())
+        {
+            snake_undo.restore(snake);
+        }
+        self.turn = undo.turn;
+        self.dead_snakes = undo.dead_snakes;
+    }
 
-        self.eliminate_dead_snakes(&mut new_board);
+    fn apply_moves(&mut self, you: Direction, others: &[Direction], mut undo: Option<&mut Undo>) {
+        let map = self.map.get();
+        let you_alive = !self.you.dead();
+        // None of the helpers below look at self.board, it is replaced by new_board at the end
+        let mut new_board = std::mem::take(&mut self.board);
+        map.pre_update(&mut new_board);
 
-        if self.death_by_collission(&self.you, &new_board) {
-            self.you.health = 0;
+        if let Some(undo) = undo.as_deref_mut() {
+            for snake in std::iter::once(&self.you).chain(self.others.iter()) {
+                undo.snakes.push(SnakeUndo::new(snake));
+            }
+        }
+        let tail = self.you.apply_move(you, &mut new_board, &self.rules, map);
+        if let Some(undo) = undo.as_deref_mut() {
+            undo.snakes[0].tail = tail;
+        }
+        for i in 0..others.len() {
+            let tail = self.others[i].apply_move(others[i], &mut new_board, &self.rules, map);
+            if let Some(undo) = undo.as_deref_mut() {
+                undo.snakes[i + 1].tail = tail;
+            }
         }
+        if self.rules.squad.allow_body_collisions {
+            // Moving tails can clear tiles still covered by a teammate
+            self.draw_bodies(&mut new_board);
+        }
+
+        let mut dead_squads = self.dead_squads();
+        let mut eliminated = self.eliminate_dead_snakes(&mut new_board, undo.as_deref_mut());
 
+        let you_died = self.death_by_collission(&self.you, &new_board);
         let mut deaths = Vec::new();
         for snake in self.others.iter() {
             deaths.push(self.death_by_collission(snake, &new_board));
         }
+        if you_died {
+            self.you.health = 0;
+        }
         for i in 0..deaths.len() {
             if deaths[i] {
                 self.others[i].health = 0;
             }
         }
 
-        self.eliminate_dead_snakes(&mut new_board);
+        if self.rules.squad.shared_elimination {
+            dead_squads.append(&mut self.dead_squads());
+            for snake in std::iter::once(&mut self.you).chain(self.others.iter_mut()) {
+                if dead_squads.contains(&snake.squad) {
+                    snake.health = 0;
+                }
+            }
+        }
+
+        eliminated += self.eliminate_dead_snakes(&mut new_board, undo);
+        if you_alive && self.you.dead() {
+            eliminated += 1;
+        }
+        self.dead_snakes += eliminated;
+        if self.rules.squad.shared_health || self.rules.squad.shared_length {
+            self.share_squad_stats();
+        }
         self.draw_heads(&mut new_board);
 
         self.board = new_board;
         self.turn += 1;
+        map.post_update(self);
+    }
+
+    /// zobrist identifies the position: the board, the bodies, lengths and
+    /// health buckets of the snakes that are alive and the parity of the turn.
+    pub fn zobrist(&self) -> u64 {
+        let mut hash = self.board.zobrist() ^ zobrist::turn_key(self.turn);
+        for snake in std::iter::once(&self.you).chain(self.others.iter()) {
+            if !snake.dead() {
+                hash ^= zobrist::snake_key(snake);
+            }
+        }
+        hash
+    }
+
+    /// exact_zobrist identifies the position like zobrist, but with the exact
+    /// health of the snakes, the exact turn and the number of dead snakes.
+    /// Positions that only differ in those can still score differently.
+    pub fn exact_zobrist(&self) -> u64 {
+        let mut hash = self.zobrist()
+            ^ zobrist::exact_turn_key(self.turn)
+            ^ zobrist::dead_snakes_key(self.dead_snakes);
+        for snake in std::iter::once(&self.you).chain(self.others.iter()) {
+            if !snake.dead() {
+                hash ^= zobrist::health_key(snake);
+            }
+        }
+        hash
+    }
+
+    /// seen_by returns the game from the point of view of another snake: that
+    /// snake is you and you is one of the others. Returns None if the snake
+    /// was eliminated.
+    pub fn seen_by(&self, id: u8) -> Option<Game> {
+        if self.you.id == id {
+            return Some(self.clone());
+        }
+        let idx = self.others.iter().position(|s| s.id == id)?;
+        let mut game = self.clone();
+        std::mem::swap(&mut game.you, &mut game.others[idx]);
+        Some(game)
+    }
+
+    /// royale_zone returns the area expected to be free of royale hazards
+    /// the given number of turns from now, or None if hazards don't grow.
+    /// When the seed of the game is known, this is exact. Otherwise the
+    /// zone is reconstructed from the current hazards and the next sides to
+    /// shrink are guessed by SafeZone::predicted_side().
+    pub fn royale_zone(&self, turns_ahead: usize) -> Option<SafeZone> {
+        self.predict_royale_zone(self.turn, self.turn + turns_ahead)
+    }
+
+    // board_turn is the turn the hazards currently on the board belong to.
+    fn predict_royale_zone(&self, board_turn: usize, turn: usize) -> Option<SafeZone> {
+        let shrink_every_n_turns = self.rules.shrink_every_n_turns;
+        if shrink_every_n_turns == 0 || self.map != MapKind::Royale {
+            return None;
+        }
+        let (w, h) = (self.board.width(), self.board.height());
+
+        if let Some(seed) = self.rules.seed {
+            return Some(royale::safe_zone(w, h, turn, shrink_every_n_turns, seed));
+        }
+
+        let mut zone = SafeZone::from_board(&self.board);
+        let shrinks = royale::shrink_count(turn, shrink_every_n_turns)
+            - royale::shrink_count(board_turn, shrink_every_n_turns);
+        for _ in 0..shrinks {
+            zone.shrink(zone.predicted_side(w, h));
+        }
+        Some(zone)
+    }
+
+    /// royale_hazards lists the tiles expected to be covered by royale
+    /// hazards the given number of turns from now.
+    pub fn royale_hazards(&self, turns_ahead: usize) -> Vec<Point> {
+        match self.royale_zone(turns_ahead) {
+            Some(zone) => zone.hazards(self.board.width(), self.board.height()),
+            None => vec![],
+        }
+    }
+
+    pub(crate) fn shrink_royale_zone(&mut self) {
+        let shrink_every_n_turns = self.rules.shrink_every_n_turns;
+        if !self.turn.is_multiple_of(shrink_every_n_turns) {
+            return;
+        }
+
+        // The board still holds the hazards of the previous turn
+        if let Some(zone) = self.predict_royale_zone(self.turn - 1, self.turn) {
+            for p in zone.hazards(self.board.width(), self.board.height()) {
+                if self.board.hazard_count(&p) == 0 {
+                    self.board.add(&p, Tile::Hazard(1));
+                }
+            }
+        }
+    }
+
+    /// spawn_food follows the official food spawning rules: if there is less
+    /// food on the board than the minimum, the board is topped up. Otherwise a
+    /// single piece of food spawns with a chance of food_spawn_chance percent.
+    pub fn spawn_food<R: Rng>(&mut self, rng: &mut R) {
+        self.spawn_food_where(rng, |_| true)
+    }
+
+    /// spawn_food_where spawns food like spawn_food, on the tiles accepted
+    /// by the filter only.
+    pub fn spawn_food_where<R: Rng, F: Fn(&Point) -> bool>(&mut self, rng: &mut R, filter: F) {
+        let food_count = self.board.bits().food.count();
+        let minimum_food = self.rules.minimum_food as usize;
+        let food_needed = if food_count < minimum_food {
+            minimum_food - food_count
+        } else if self.rules.food_spawn_chance > 0
+            && 100 - rng.gen_range(0..100) < self.rules.food_spawn_chance
+        {
+            1
+        } else {
+            0
+        };
+        if food_needed == 0 {
+            return;
+        }
+
+        let mut free_tiles = vec![];
+        for x in 0..self.board.width() {
+            for y in 0..self.board.height() {
+                let p = Point {
+                    x: x as i8,
+                    y: y as i8,
+                };
+                // Food never spawns right in front of a snake's head
+                if self.board.get(&p) == Tile::Empty && filter(&p) && !self.next_to_head(&p) {
+                    free_tiles.push(p);
+                }
+            }
+        }
+
+        for _ in 0..food_needed {
+            if free_tiles.is_empty() {
+                break;
+            }
+            let p = free_tiles.swap_remove(rng.gen_range(0..free_tiles.len()));
+            self.board.add(&p, Tile::Food);
+        }
+    }
+
+    fn next_to_head(&self, p: &Point) -> bool {
+        let heads = std::iter::once(&self.you)
+            .filter(|s| !s.dead())
+            .chain(self.others.iter())
+            .map(|s| s.head);
+        for head in heads {
+            for (_, mut n) in head.neighbours() {
+                self.warp(&mut n);
+                if &n == p {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn dead_squads(&self) -> Vec<u8> {
+        std::iter::once(&self.you)
+            .chain(self.others.iter())
+            .filter(|s| s.dead() && s.squad != 0)
+            .map(|s| s.squad)
+            .collect()
+    }
+
+    fn share_squad_stats(&mut self) {
+        let mut stats: Vec<(u8, i8, usize)> = vec![];
+        for snake in std::iter::once(&self.you).chain(self.others.iter()) {
+            if snake.dead() || snake.squad == 0 {
+                continue;
+            }
+            match stats.iter_mut().find(|(squad, _, _)| *squad == snake.squad) {
+                Some((_, health, length)) => {
+                    *health = cmp::max(*health, snake.health);
+                    *length = cmp::max(*length, snake.length);
+                }
+                None => stats.push((snake.squad, snake.health, snake.length)),
+            }
+        }
+
+        for snake in std::iter::once(&mut self.you).chain(self.others.iter_mut()) {
+            if snake.dead() {
+                continue;
+            }
+            if let Some((_, health, length)) = stats.iter().find(|(s, _, _)| *s == snake.squad) {
+                if self.rules.squad.shared_health {
+                    snake.health = *health;
+                }
+                if self.rules.squad.shared_length {
+                    while snake.length < *length {
+                        snake.length += 1;
+                        snake.body.push_back(*snake.body.back().unwrap());
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_bodies(&self, board: &mut Board) {
+        for snake in std::iter::once(&self.you).chain(self.others.iter()) {
+            if snake.dead() {
+                continue;
+            }
+            for p in snake.body.iter().skip(1) {
+                board.add(p, Tile::Snake);
+            }
+        }
     }
 
     fn draw_heads(&self, board: &mut Board) {
@@ -97,7 +500,20 @@ impl Game {
 
     fn death_by_collission(&self, snake: &Snake, board: &Board) -> bool {
         match board.get(&snake.head) {
-            Tile::HazardWithSnake(_) | Tile::Snake => return true,
+            Tile::Wall => return true,
+            Tile::HazardWithSnake(_) | Tile::Snake => {
+                if !self.rules.squad.allow_body_collisions {
+                    return true;
+                }
+                // Only bodies of snakes outside of the squad are deadly
+                let hit = std::iter::once(&self.you)
+                    .chain(self.others.iter())
+                    .filter(|other| !other.dead() && !snake.same_squad(other))
+                    .any(|other| other.body.iter().skip(1).any(|p| p == &snake.head));
+                if hit {
+                    return true;
+                }
+            }
             _ => {}
         }
 
@@ -148,26 +564,35 @@ impl Game {
         }
     }
 
-    fn eliminate_dead_snakes(&mut self, new_board: &mut Board) {
+    // Returns the number of other snakes removed, a dead you stays in the game
+    fn eliminate_dead_snakes(&mut self, new_board: &mut Board, undo: Option<&mut Undo>) -> usize {
         let mut dead_snakes = 0;
         let mut crash_sites = vec![];
         if self.you.dead() {
             self.you.remove_from_board(new_board);
             crash_sites.push(self.you.head.clone());
-            dead_snakes += 1;
         }
-        self.others.retain(|snake| {
-            if snake.dead() {
+        let mut removed = vec![];
+        let mut i = 0;
+        while i < self.others.len() {
+            if self.others[i].dead() {
+                let snake = self.others.remove(i);
                 snake.remove_from_board(new_board);
                 crash_sites.push(snake.head.clone());
                 dead_snakes += 1;
-                false
+                removed.push((i, snake));
             } else {
-                true
+                i += 1;
             }
-        });
-        self.dead_snakes = dead_snakes;
+        }
+        if let Some(undo) = undo {
+            undo.removed.append(&mut removed);
+        }
         self.repair_crash_sites(&crash_sites, new_board);
+        if self.rules.squad.allow_body_collisions {
+            self.draw_bodies(new_board);
+        }
+        dead_snakes
     }
 
     fn snake_number(&self, p: &Point) -> isize {
@@ -198,6 +623,13 @@ impl Game {
     }
 }
 
+// Hashing the full state is slow, the zobrist hash identifies the position
+impl Hash for Game {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        state.write_u64(self.zobrist())
+    }
+}
+
 impl std::fmt::Display for Game {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("Turn {}\n", self.turn))?;
@@ -232,12 +664,25 @@ impl std::fmt::Display for Game {
     }
 }
 
-impl From<&protocol::Request> for Game {
-    fn from(req: &protocol::Request) -> Self {
+impl TryFrom<&protocol::Request> for Game {
+    type Error = String;
+
+    fn try_from(req: &protocol::Request) -> Result<Self, Self::Error> {
+        Game::validate(req)?;
         let board: Board = (&req.board).into();
 
+        // Squads are named by the engine, number them in order of appearance
+        let mut squads = HashMap::new();
+        for s in std::iter::once(&req.you).chain(req.board.snakes.iter()) {
+            if !s.squad.is_empty() && !squads.contains_key(&s.squad) {
+                squads.insert(s.squad.clone(), squads.len() as u8 + 1);
+            }
+        }
+        let squad = |s: &protocol::Snake| *squads.get(&s.squad).unwrap_or(&0);
+
         let mut you = Snake::from(&req.you);
         you.id = 0;
+        you.squad = squad(&req.you);
 
         let others = req
             .board
@@ -246,40 +691,78 @@ impl From<&protocol::Request> for Game {
             .filter(|s| s.id != req.you.id)
             .enumerate()
             .map(|(i, s)| {
-                let mut s = Snake::from(s);
-                s.id = i as u8 + 1;
-                s
+                let mut snake = Snake::from(s);
+                snake.id = i as u8 + 1;
+                snake.squad = squad(s);
+                snake
             })
             .collect();
 
-        Game {
+        let mut rules = Rules::from(&req.game.ruleset);
+        let map = match map_kind(rules.game_mode, &req.game.map) {
+            Some(map) => map,
+            None => {
+                log!(
+                    "unknown map: {}, hazards are assumed to be static",
+                    req.game.map
+                );
+                MapKind::Standard
+            }
+        };
+        if map == MapKind::Royale || map == MapKind::HazardPits {
+            rules.shrink_every_n_turns = req.game.ruleset.settings.royale.shrink_every_n_turns;
+        }
+        if !map.get().fits(board.width(), board.height()) {
+            log!(
+                "Error: no layout of {} for {}x{} boards, its hazards are not updated",
+                map.get().name(),
+                board.width(),
+                board.height()
+            );
+        }
+
+        Ok(Game {
             board: board,
             timeout: std::time::Duration::from_millis(req.game.timeout as u64),
             you,
             others,
-            rules: Rules::from(&req.game.ruleset),
+            rules,
             dead_snakes: 0,
             turn: req.turn,
-            snail_mode: req.game.map == "snail_mode",
-        }
+            map,
+            metadata: Arc::new(Metadata::from(req)),
+        })
     }
 }
 
 impl From<&protocol::Ruleset> for Rules {
     fn from(r: &protocol::Ruleset) -> Self {
+        let game_mode = GameMode::from_name(&r.name).unwrap_or_else(|| {
+            log!("unknown game mode: {}", r.name);
+            GameMode::Standard
+        });
+
         Self {
-            game_mode: match r.name.as_str() {
-                "standard" => GameMode::Standard,
-                "royale" => GameMode::Royale,
-                "wrapped" => GameMode::Wrapped,
-                "constrictor" => GameMode::Constrictor,
-                "solo" => GameMode::Solo,
-                _ => {
-                    println!("unknown game mode: {}", r.name);
-                    GameMode::Standard
+            game_mode,
+            hazard_damage_per_turn: r.settings.hazard_damage_per_turn as i8,
+            food_spawn_chance: r.settings.food_spawn_chance as u8,
+            minimum_food: r.settings.minimum_food as u8,
+            shrink_every_n_turns: if game_mode == GameMode::Royale {
+                r.settings.royale.shrink_every_n_turns
+            } else {
+                0
+            },
+            squad: if game_mode == GameMode::Squad {
+                SquadRules {
+                    allow_body_collisions: r.settings.squad.allow_body_collisions,
+                    shared_elimination: r.settings.squad.shared_elimination,
+                    shared_health: r.settings.squad.shared_health,
+                    shared_length: r.settings.squad.shared_length,
                 }
+            } else {
+                SquadRules::default()
             },
-            hazard_damage_per_turn: r.settings.hazard_damage_per_turn as i8,
+            seed: None,
         }
     }
 }
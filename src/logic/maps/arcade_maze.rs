@@ -0,0 +1,90 @@
+use rand::{seq::SliceRandom, RngCore};
+
+use super::{food_rng, layout_fits, layout_points, Map};
+use crate::logic::{Game, Point, Tile};
+
+const MAZE: [&str; 21] = [
+    "###################",
+    "#........#........#",
+    "#.##.###.#.###.##.#",
+    "#.................#",
+    "#.##.#.#####.#.##.#",
+    "#....#...#...#....#",
+    "####.###.#.###.####",
+    "####.#.......#.####",
+    "####.#.##.##.#.####",
+    ".......#...#.......",
+    "####.#.#####.#.####",
+    "####.#.......#.####",
+    "####.#.#####.#.####",
+    "#........#........#",
+    "#.##.###.#.###.##.#",
+    "#..#...........#..#",
+    "##.#.#.#####.#.#.##",
+    "#....#...#...#....#",
+    "#.######.#.######.#",
+    "#.................#",
+    "###################",
+];
+
+const SPAWN_POINTS: [Point; 8] = [
+    Point { x: 1, y: 1 },
+    Point { x: 17, y: 19 },
+    Point { x: 17, y: 1 },
+    Point { x: 1, y: 19 },
+    Point { x: 4, y: 9 },
+    Point { x: 14, y: 9 },
+    Point { x: 9, y: 1 },
+    Point { x: 9, y: 13 },
+];
+
+const FOOD_POINTS: [Point; 6] = [
+    Point { x: 1, y: 17 },
+    Point { x: 17, y: 17 },
+    Point { x: 1, y: 3 },
+    Point { x: 17, y: 3 },
+    Point { x: 9, y: 5 },
+    Point { x: 9, y: 11 },
+];
+
+/// ArcadeMaze is a 19x21 maze surrounded by walls, food only spawns on a
+/// few fixed tiles.
+pub struct ArcadeMaze;
+
+impl Map for ArcadeMaze {
+    fn name(&self) -> &'static str {
+        "arcade_maze"
+    }
+
+    fn spawn_points(
+        &self,
+        _width: isize,
+        _height: isize,
+        count: usize,
+        rng: &mut dyn RngCore,
+    ) -> Vec<Point> {
+        let mut points = SPAWN_POINTS.to_vec();
+        points.shuffle(rng);
+        points.truncate(count);
+        points
+    }
+
+    fn fits(&self, width: isize, height: isize) -> bool {
+        layout_fits(&MAZE, width, height)
+    }
+
+    fn setup_board(&self, game: &mut Game, _rng: &mut dyn RngCore) {
+        if !self.fits(game.board.width(), game.board.height()) {
+            return;
+        }
+        for p in layout_points(&MAZE, '#') {
+            game.board.set(&p, Tile::Wall);
+        }
+    }
+
+    fn post_update(&self, game: &mut Game) {
+        if let Some(mut rng) = food_rng(game) {
+            game.spawn_food_where(&mut rng, |p| FOOD_POINTS.contains(p));
+        }
+    }
+}
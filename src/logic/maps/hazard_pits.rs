@@ -0,0 +1,70 @@
+use super::{food_rng, layout_fits, layout_points, Map};
+use crate::logic::{board, Game, Tile};
+
+const PITS: [&str; 11] = [
+    "...........",
+    ".x.......x.",
+    "...........",
+    "...x...x...",
+    "...........",
+    ".....x.....",
+    "...........",
+    "...x...x...",
+    "...........",
+    ".x.......x.",
+    "...........",
+];
+
+const PIT_MAX_DEPTH: u8 = 4;
+
+/// pit_depth is the number of hazards stacked in every pit on the given turn,
+/// when a layer is added every fill_every_n_turns turns. The pits fill up one
+/// layer at a time and are emptied when they are full.
+pub fn pit_depth(turn: usize, fill_every_n_turns: usize) -> u8 {
+    let layers = turn.checked_div(fill_every_n_turns).unwrap_or(0);
+    (layers % (PIT_MAX_DEPTH as usize + 1)) as u8
+}
+
+/// HazardPits has a fixed set of pits, which are periodically filled with
+/// stacked hazards, every Rules::shrink_every_n_turns turns. Layouts are only
+/// known for 11x11 boards.
+pub struct HazardPits;
+
+impl Map for HazardPits {
+    fn name(&self) -> &'static str {
+        "hz_hazard_pits"
+    }
+
+    fn fits(&self, width: isize, height: isize) -> bool {
+        layout_fits(&PITS, width, height)
+    }
+
+    fn post_update(&self, game: &mut Game) {
+        if let Some(mut rng) = food_rng(game) {
+            game.spawn_food(&mut rng);
+        }
+        self.post_update_pits(game);
+    }
+}
+
+impl HazardPits {
+    // The pits only change when a layer is added or when they are emptied,
+    // in between the hazards on the board are kept as they are.
+    fn post_update_pits(&self, game: &mut Game) {
+        let every_n_turns = game.rules.shrink_every_n_turns;
+        if every_n_turns == 0
+            || !game.turn.is_multiple_of(every_n_turns)
+            || !self.fits(game.board.width(), game.board.height())
+        {
+            return;
+        }
+        let empty = pit_depth(game.turn, every_n_turns) == 0;
+        for p in layout_points(&PITS, 'x') {
+            if empty {
+                game.board.remove_hazards(&p, board::MAX_HAZARDS);
+            } else {
+                game.board.add(&p, Tile::Hazard(1));
+            }
+        }
+    }
+}
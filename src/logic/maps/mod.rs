@@ -0,0 +1,133 @@
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+use super::{Board, Game, Point, Snake};
+
+mod arcade_maze;
+mod hazard_pits;
+mod royale;
+mod snail_mode;
+mod standard;
+mod static_hazards;
+
+pub use arcade_maze::ArcadeMaze;
+pub use hazard_pits::{pit_depth, HazardPits};
+pub use royale::Royale;
+pub use snail_mode::SnailMode;
+pub use standard::Standard;
+pub use static_hazards::{IslandsBridges, RiversAndLakes};
+
+/// Map implements the board changes that are specific to a map: where snakes
+/// start, which hazards and walls are placed and how hazards and food change
+/// from turn to turn.
+pub trait Map: Sync {
+    fn name(&self) -> &'static str;
+
+    /// spawn_points picks the starting positions for count snakes.
+    fn spawn_points(
+        &self,
+        width: isize,
+        height: isize,
+        count: usize,
+        rng: &mut dyn RngCore,
+    ) -> Vec<Point> {
+        standard::spawn_points(width, height, count, rng)
+    }
+
+    /// fits tells whether the map knows its layout for boards of the given
+    /// size. Maps without a fixed layout fit any board.
+    fn fits(&self, _width: isize, _height: isize) -> bool {
+        true
+    }
+
+    /// setup_board places the hazards, walls and initial food of a new game,
+    /// after the snakes have been placed on the board.
+    fn setup_board(&self, game: &mut Game, rng: &mut dyn RngCore) {
+        standard::place_initial_food(game, rng);
+    }
+
+    /// pre_update is called on the new board before any snake moves.
+    fn pre_update(&self, _board: &mut Board) {}
+
+    /// tail_hazards is the number of hazards a snake leaves behind on the
+    /// tile its tail moved away from.
+    fn tail_hazards(&self, _snake: &Snake) -> u8 {
+        0
+    }
+
+    /// post_update is called once all moves are resolved and the turn
+    /// counter has been incremented.
+    fn post_update(&self, game: &mut Game) {
+        if let Some(mut rng) = food_rng(game) {
+            game.spawn_food(&mut rng);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum MapKind {
+    Standard,
+    Royale,
+    SnailMode,
+    RiversAndLakes,
+    IslandsBridges,
+    HazardPits,
+    ArcadeMaze,
+}
+
+impl MapKind {
+    pub fn from_name(name: &str) -> Option<MapKind> {
+        match name {
+            "standard" | "empty" | "" => Some(MapKind::Standard),
+            "royale" => Some(MapKind::Royale),
+            "snail_mode" => Some(MapKind::SnailMode),
+            "hz_rivers_and_lakes" => Some(MapKind::RiversAndLakes),
+            "hz_islands_bridges" => Some(MapKind::IslandsBridges),
+            "hz_hazard_pits" => Some(MapKind::HazardPits),
+            "arcade_maze" => Some(MapKind::ArcadeMaze),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self) -> &'static dyn Map {
+        match self {
+            MapKind::Standard => &Standard,
+            MapKind::Royale => &Royale,
+            MapKind::SnailMode => &SnailMode,
+            MapKind::RiversAndLakes => &RiversAndLakes,
+            MapKind::IslandsBridges => &IslandsBridges,
+            MapKind::HazardPits => &HazardPits,
+            MapKind::ArcadeMaze => &ArcadeMaze,
+        }
+    }
+}
+
+/// food_rng returns the random generator for the food spawned this turn,
+/// or None when the seed of the game is unknown.
+fn food_rng(game: &Game) -> Option<StdRng> {
+    game.rules
+        .seed
+        .map(|seed| StdRng::seed_from_u64(seed.wrapping_add(game.turn as u64)))
+}
+
+/// layout_points converts a map drawn as rows of text into the list of
+/// points marked with c. The first row is the top of the board.
+fn layout_points(layout: &[&str], c: char) -> Vec<Point> {
+    let height = layout.len() as i8;
+    let mut res = vec![];
+    for (row, line) in layout.iter().enumerate() {
+        for (x, tile) in line.chars().enumerate() {
+            if tile == c {
+                res.push(Point {
+                    x: x as i8,
+                    y: height - 1 - row as i8,
+                });
+            }
+        }
+    }
+    res
+}
+
+fn layout_fits(layout: &[&str], width: isize, height: isize) -> bool {
+    layout.len() as isize == height && layout.iter().all(|l| l.len() as isize == width)
+}
@@ -0,0 +1,21 @@
+use super::{food_rng, Map};
+use crate::logic::Game;
+
+/// Royale covers one edge of the safe zone in hazards every
+/// Rules::shrink_every_n_turns turns, see Game::royale_zone().
+pub struct Royale;
+
+impl Map for Royale {
+    fn name(&self) -> &'static str {
+        "royale"
+    }
+
+    fn post_update(&self, game: &mut Game) {
+        if let Some(mut rng) = food_rng(game) {
+            game.spawn_food(&mut rng);
+        }
+        if game.rules.shrink_every_n_turns > 0 {
+            game.shrink_royale_zone();
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use std::cmp;
+
+use super::Map;
+use crate::logic::{board, Board, Point, Snake};
+
+/// SnailMode leaves a trail of hazards behind every snake, as many as the
+/// snake is long. The trail decays by one hazard every turn.
+pub struct SnailMode;
+
+impl Map for SnailMode {
+    fn name(&self) -> &'static str {
+        "snail_mode"
+    }
+
+    fn pre_update(&self, board: &mut Board) {
+        for x in 0..board.width() {
+            for y in 0..board.height() {
+                let p = Point {
+                    x: x as i8,
+                    y: y as i8,
+                };
+                board.remove_hazards(&p, 1)
+            }
+        }
+    }
+
+    fn tail_hazards(&self, snake: &Snake) -> u8 {
+        cmp::min(snake.length, board::MAX_HAZARDS as usize) as u8
+    }
+}
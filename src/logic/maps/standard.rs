@@ -0,0 +1,103 @@
+use rand::{seq::SliceRandom, Rng, RngCore};
+
+use super::Map;
+use crate::logic::{Game, Point, Tile};
+
+pub struct Standard;
+
+impl Map for Standard {
+    fn name(&self) -> &'static str {
+        "standard"
+    }
+}
+
+/// spawn_points uses the fixed starting positions of the official rules on
+/// the common board sizes, and random tiles with an even parity otherwise.
+pub(super) fn spawn_points(
+    width: isize,
+    height: isize,
+    count: usize,
+    rng: &mut dyn RngCore,
+) -> Vec<Point> {
+    if width == height && [7, 11, 19].contains(&width) && count <= 8 {
+        let (mn, md, mx) = (1, (width as i8 - 1) / 2, width as i8 - 2);
+        let mut corners = vec![
+            Point { x: mn, y: mn },
+            Point { x: mn, y: mx },
+            Point { x: mx, y: mn },
+            Point { x: mx, y: mx },
+        ];
+        let mut cardinals = vec![
+            Point { x: mn, y: md },
+            Point { x: md, y: mn },
+            Point { x: md, y: mx },
+            Point { x: mx, y: md },
+        ];
+        corners.shuffle(rng);
+        cardinals.shuffle(rng);
+        let mut points = if rng.gen_bool(0.5) {
+            corners.append(&mut cardinals);
+            corners
+        } else {
+            cardinals.append(&mut corners);
+            cardinals
+        };
+        points.truncate(count);
+        return points;
+    }
+
+    let mut points = vec![];
+    for x in 0..width as i8 {
+        for y in 0..height as i8 {
+            if (x + y) % 2 == 0 {
+                points.push(Point { x, y });
+            }
+        }
+    }
+    points.shuffle(rng);
+    points.truncate(count);
+    points
+}
+
+/// place_initial_food puts one piece of food diagonally next to every snake,
+/// away from the center of the board, and one in the center.
+pub(super) fn place_initial_food(game: &mut Game, rng: &mut dyn RngCore) {
+    let (w, h) = (game.board.width(), game.board.height());
+    let center = Point {
+        x: (w as i8 - 1) / 2,
+        y: (h as i8 - 1) / 2,
+    };
+    let heads: Vec<Point> = std::iter::once(&game.you)
+        .chain(game.others.iter())
+        .map(|s| s.head)
+        .collect();
+
+    // On small boards, there's only room for food next to 4 snakes
+    if heads.len() <= 4 || w * h >= 11 * 11 {
+        for head in heads {
+            let candidates: Vec<Point> = [(-1, -1), (-1, 1), (1, -1), (1, 1)]
+                .iter()
+                .map(|(dx, dy)| Point {
+                    x: head.x + dx,
+                    y: head.y + dy,
+                })
+                .filter(|p| {
+                    let away_from_center = (p.x < head.x && head.x < center.x)
+                        || (center.x < head.x && head.x < p.x)
+                        || (p.y < head.y && head.y < center.y)
+                        || (center.y < head.y && head.y < p.y);
+                    let corner =
+                        (p.x == 0 || p.x as isize == w - 1) && (p.y == 0 || p.y as isize == h - 1);
+                    *p != center && away_from_center && !corner && game.board.get(p) == Tile::Empty
+                })
+                .collect();
+            if let Some(p) = candidates.choose(rng) {
+                game.board.add(p, Tile::Food);
+            }
+        }
+    }
+
+    if game.board.get(&center) == Tile::Empty {
+        game.board.add(&center, Tile::Food);
+    }
+}
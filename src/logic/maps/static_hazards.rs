@@ -0,0 +1,80 @@
+use rand::RngCore;
+
+use super::{layout_fits, layout_points, standard, Map};
+use crate::logic::{Board, Game, Tile};
+
+// Layouts for 11x11 boards, other board sizes start without hazards.
+const RIVERS_AND_LAKES: [&str; 11] = [
+    ".....x.....",
+    ".xx..x..xx.",
+    ".xx.....xx.",
+    ".....x.....",
+    "...........",
+    "x.xx.x.xx.x",
+    "...........",
+    ".....x.....",
+    ".xx.....xx.",
+    ".xx..x..xx.",
+    ".....x.....",
+];
+
+const ISLANDS_BRIDGES: [&str; 11] = [
+    "xx..xxx..xx",
+    "x....x....x",
+    "...........",
+    ".....x.....",
+    "x....x....x",
+    "xx.xxxxx.xx",
+    "x....x....x",
+    ".....x.....",
+    "...........",
+    "x....x....x",
+    "xx..xxx..xx",
+];
+
+/// place_hazards covers the tiles marked with x in the layout in a single
+/// hazard, if the layout matches the size of the board.
+pub(super) fn place_hazards(layout: &[&str], board: &mut Board) {
+    if !layout_fits(layout, board.width(), board.height()) {
+        return;
+    }
+    for p in layout_points(layout, 'x') {
+        board.add(&p, Tile::Hazard(1));
+    }
+}
+
+/// RiversAndLakes has hazards that never change after the first turn.
+pub struct RiversAndLakes;
+
+impl Map for RiversAndLakes {
+    fn name(&self) -> &'static str {
+        "hz_rivers_and_lakes"
+    }
+
+    fn fits(&self, width: isize, height: isize) -> bool {
+        layout_fits(&RIVERS_AND_LAKES, width, height)
+    }
+
+    fn setup_board(&self, game: &mut Game, rng: &mut dyn RngCore) {
+        place_hazards(&RIVERS_AND_LAKES, &mut game.board);
+        standard::place_initial_food(game, rng);
+    }
+}
+
+/// IslandsBridges has hazards that never change after the first turn.
+pub struct IslandsBridges;
+
+impl Map for IslandsBridges {
+    fn name(&self) -> &'static str {
+        "hz_islands_bridges"
+    }
+
+    fn fits(&self, width: isize, height: isize) -> bool {
+        layout_fits(&ISLANDS_BRIDGES, width, height)
+    }
+
+    fn setup_board(&self, game: &mut Game, rng: &mut dyn RngCore) {
+        place_hazards(&ISLANDS_BRIDGES, &mut game.board);
+        standard::place_initial_food(game, rng);
+    }
+}
@@ -3,8 +3,13 @@ use std::fmt::Display;
 #[cfg(test)]
 mod tests;
 
+pub mod bitboard;
 pub mod floodfill;
+pub mod maps;
+pub mod notation;
+pub mod royale;
 pub mod scoring;
+pub mod zobrist;
 
 mod board;
 pub use board::Board;
@@ -15,6 +20,9 @@ pub use game::Game;
 mod point;
 pub use point::Point;
 
+mod request;
+pub use request::Metadata;
+
 mod snake;
 pub use snake::Snake;
 
@@ -0,0 +1,432 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::protocol::{self, Direction, ALL_DIRECTIONS};
+
+use super::{game::GameMode, Game, Point, Tile};
+
+// A position is written as a list of settings, followed by the rows of the
+// board from top to bottom, with one token per tile:
+//
+//     ruleset: wrapped
+//     turn: 12
+//     A: health=90
+//     B: health=45 length=5 squad=red
+//     . . + . .
+//     . > A . .
+//     . ^ . #!2 .
+//     . B < < .
+//     . . . .! .
+//
+// Tokens:
+//   .          empty tile
+//   +          food
+//   #          wall
+//   A-Z        the head of a snake, A is you unless set with "you: B", or
+//              with "you: -" for a snake that is no longer on the board
+//   ^ v < >    a body segment, pointing towards the next segment closer
+//              to the head
+//   !n         appended to any token, n stacked hazards (n is 1 when left out)
+//
+// Snakes are numbered in alphabetical order. Tails stacked on the same tile,
+// like at the start of a game, are written as a length that is larger than
+// the number of segments on the board.
+//
+// Settings: ruleset, map, turn, timeout, seed, you, hazard_damage,
+// food_spawn_chance, minimum_food, shrink_every_n_turns,
+// allow_body_collisions, shared_elimination, shared_health, shared_length,
+// and a line per snake with health, length, squad and name.
+
+/// parse_game builds the game described in the notation above.
+pub fn parse_game(text: &str) -> Result<Game, String> {
+    let position = parse(text)?;
+    let mut game = Game::try_from(&position.request)?;
+    for p in position.walls.iter() {
+        game.board.set(p, Tile::Wall);
+    }
+    game.rules.seed = position.seed;
+    Ok(game)
+}
+
+/// parse_request builds the request for the position described in the
+/// notation above. Walls can't be sent in a request.
+pub fn parse_request(text: &str) -> Result<protocol::Request, String> {
+    let position = parse(text)?;
+    if !position.walls.is_empty() {
+        return Err("walls are not part of a request".to_string());
+    }
+    Ok(position.request)
+}
+
+/// write describes the game in the notation above. Snakes keep their id as
+/// letter if it is one, so parsed positions are written back unchanged.
+pub fn write(game: &Game) -> String {
+    let req = protocol::Request::from(game);
+    let (w, h) = (req.board.width, req.board.height);
+    let mut res = format!("ruleset: {}\n", req.game.ruleset.name);
+    if req.game.map != "standard" {
+        res.push_str(&format!("map: {}\n", req.game.map));
+    }
+    res.push_str(&format!("turn: {}\n", req.turn));
+    let settings = &req.game.ruleset.settings;
+    res.push_str(&format!(
+        "hazard_damage: {}\n",
+        settings.hazard_damage_per_turn
+    ));
+    if let Some(seed) = game.rules.seed {
+        res.push_str(&format!("seed: {}\n", seed));
+    }
+
+    let mut used: Vec<char> = req
+        .board
+        .snakes
+        .iter()
+        .filter_map(|s| letter(&s.id))
+        .collect();
+    used.sort_unstable();
+    used.dedup();
+    let mut letters = HashMap::new();
+    let mut unused = ('A'..='Z').filter(|c| !used.contains(c));
+    for s in req.board.snakes.iter() {
+        let c = match letter(&s.id) {
+            Some(c) if used.len() == req.board.snakes.len() => c,
+            _ => unused.next().unwrap_or('?'),
+        };
+        letters.insert(s.id.clone(), c);
+    }
+    match letters.get(&req.you.id) {
+        Some(c) if Some(c) != letters.values().min() => res.push_str(&format!("you: {}\n", c)),
+        Some(_) => {}
+        None => res.push_str("you: -\n"),
+    }
+
+    let mut tokens = vec![vec![String::new(); w]; h];
+    let mut snakes: Vec<_> = req.board.snakes.iter().collect();
+    snakes.sort_by_key(|s| letters[&s.id]);
+    for snake in snakes {
+        let c = letters[&snake.id];
+        res.push_str(&format!(
+            "{}: health={} length={}",
+            c, snake.health, snake.length
+        ));
+        if !snake.squad.is_empty() {
+            res.push_str(&format!(" squad={}", snake.squad));
+        }
+        if snake.name != snake.id {
+            res.push_str(&format!(" name={}", snake.name));
+        }
+        res.push('\n');
+
+        tokens[snake.head.y as usize][snake.head.x as usize] = c.to_string();
+        for (prev, p) in snake.body.iter().zip(snake.body.iter().skip(1)) {
+            if p == prev {
+                continue;
+            }
+            let arrow = ALL_DIRECTIONS
+                .into_iter()
+                .find(|d| {
+                    let mut n = p.neighbour(*d);
+                    game.warp(&mut n);
+                    &n == prev
+                })
+                .map_or("?", arrow);
+            tokens[p.y as usize][p.x as usize] = arrow.to_string();
+        }
+    }
+
+    for y in (0..h).rev() {
+        let row: Vec<String> = (0..w)
+            .map(|x| {
+                let p = Point {
+                    x: x as i8,
+                    y: y as i8,
+                };
+                let mut token = tokens[y][x].clone();
+                if token.is_empty() {
+                    token = match game.board.get(&p) {
+                        Tile::Wall => "#",
+                        t if t.has_food() => "+",
+                        _ => ".",
+                    }
+                    .to_string();
+                }
+                match game.board.hazard_count(&p) {
+                    0 => token,
+                    1 => format!("{}!", token),
+                    n => format!("{}!{}", token, n),
+                }
+            })
+            .collect();
+        res.push_str(&row.join(" "));
+        res.push('\n');
+    }
+    res
+}
+
+struct Position {
+    request: protocol::Request,
+    walls: Vec<Point>,
+    seed: Option<u64>,
+}
+
+#[derive(Default)]
+struct SnakeSettings {
+    health: Option<isize>,
+    length: Option<usize>,
+    squad: String,
+    name: Option<String>,
+}
+
+// The letter of a snake with a single uppercase letter as id
+fn letter(id: &str) -> Option<char> {
+    let mut chars = id.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_uppercase() => Some(c),
+        _ => None,
+    }
+}
+
+fn arrow(dir: Direction) -> &'static str {
+    match dir {
+        Direction::Up => "^",
+        Direction::Down => "v",
+        Direction::Left => "<",
+        Direction::Right => ">",
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {}", key, value))
+}
+
+fn parse(text: &str) -> Result<Position, String> {
+    let mut ruleset = protocol::Ruleset::default();
+    let mut map = "standard".to_string();
+    let mut turn = 0;
+    let mut timeout = 500;
+    let mut seed = None;
+    let mut you = None;
+    let mut snake_settings: HashMap<char, SnakeSettings> = HashMap::new();
+    let mut rows: Vec<Vec<&str>> = vec![];
+
+    for line in text.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        let (key, value) = match line.split_once(':') {
+            Some((k, v)) => (k.trim(), v.trim()),
+            None => {
+                rows.push(line.split_whitespace().collect());
+                continue;
+            }
+        };
+        if !rows.is_empty() {
+            return Err(format!("setting after the board: {}", line));
+        }
+
+        let settings = &mut ruleset.settings;
+        match key {
+            "ruleset" => ruleset.name = value.to_string(),
+            "map" => map = value.to_string(),
+            "turn" => turn = parse_value(key, value)?,
+            "timeout" => timeout = parse_value(key, value)?,
+            "seed" => seed = Some(parse_value(key, value)?),
+            "you" => you = value.chars().next(),
+            "hazard_damage" => settings.hazard_damage_per_turn = parse_value(key, value)?,
+            "food_spawn_chance" => settings.food_spawn_chance = parse_value(key, value)?,
+            "minimum_food" => settings.minimum_food = parse_value(key, value)?,
+            "shrink_every_n_turns" => {
+                settings.royale.shrink_every_n_turns = parse_value(key, value)?
+            }
+            "allow_body_collisions" => {
+                settings.squad.allow_body_collisions = parse_value(key, value)?
+            }
+            "shared_elimination" => settings.squad.shared_elimination = parse_value(key, value)?,
+            "shared_health" => settings.squad.shared_health = parse_value(key, value)?,
+            "shared_length" => settings.squad.shared_length = parse_value(key, value)?,
+            k if k.len() == 1 && k.chars().all(|c| c.is_ascii_uppercase()) => {
+                let snake = snake_settings.entry(k.chars().next().unwrap()).or_default();
+                for field in value.split_whitespace() {
+                    let (k, v) = field
+                        .split_once('=')
+                        .ok_or_else(|| format!("invalid snake setting: {}", field))?;
+                    match k {
+                        "health" => snake.health = Some(parse_value(k, v)?),
+                        "length" => snake.length = Some(parse_value(k, v)?),
+                        "squad" => snake.squad = v.to_string(),
+                        "name" => snake.name = Some(v.to_string()),
+                        _ => return Err(format!("unknown snake setting: {}", k)),
+                    }
+                }
+            }
+            _ => return Err(format!("unknown setting: {}", key)),
+        }
+    }
+
+    let (w, h) = (rows.first().map_or(0, |r| r.len()), rows.len());
+    if w == 0 || rows.iter().any(|r| r.len() != w) {
+        return Err("all rows of the board must have the same number of tiles".to_string());
+    }
+    let wrapped = GameMode::from_name(&ruleset.name) == Some(GameMode::Wrapped);
+
+    let mut food = vec![];
+    let mut hazards = vec![];
+    let mut walls = vec![];
+    let mut heads = vec![];
+    let mut segments: HashMap<Point, Direction> = HashMap::new();
+    for (row, tokens) in rows.iter().enumerate() {
+        for (x, token) in tokens.iter().enumerate() {
+            let p = Point {
+                x: x as i8,
+                y: (h - 1 - row) as i8,
+            };
+            let (tile, hazard_count) = match token.split_once('!') {
+                Some((t, "")) => (t, 1),
+                Some((t, n)) => (t, parse_value::<usize>("hazards", n)?),
+                None => (*token, 0),
+            };
+            for _ in 0..hazard_count {
+                hazards.push(p);
+            }
+            match tile {
+                "." => {}
+                "+" => food.push(p),
+                "#" => walls.push(p),
+                "^" => _ = segments.insert(p, Direction::Up),
+                "v" => _ = segments.insert(p, Direction::Down),
+                "<" => _ = segments.insert(p, Direction::Left),
+                ">" => _ = segments.insert(p, Direction::Right),
+                t if t.len() == 1 && t.chars().all(|c| c.is_ascii_uppercase()) => {
+                    heads.push((t.chars().next().unwrap(), p))
+                }
+                t => return Err(format!("unknown tile at {}: {}", p, t)),
+            }
+        }
+    }
+    heads.sort_by_key(|(c, _)| *c);
+    for pair in heads.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            return Err(format!("snake {} has more than one head", pair[0].0));
+        }
+    }
+    if let Some(c) = snake_settings
+        .keys()
+        .find(|c| !heads.iter().any(|(h, _)| h == *c))
+    {
+        return Err(format!("snake {} is not on the board", c));
+    }
+
+    let step = |p: Point, d: Direction| {
+        let mut n = p.neighbour(d);
+        if wrapped {
+            n.warp(w as isize, h as isize);
+        }
+        n
+    };
+
+    let mut snakes = vec![];
+    for (c, head) in heads.iter() {
+        // Follow the segments pointing at the previous part of the body
+        let mut body = VecDeque::from([*head]);
+        loop {
+            let prev = *body.back().unwrap();
+            let next = ALL_DIRECTIONS.into_iter().find_map(|d| {
+                let p = step(prev, d);
+                match segments.get(&p) {
+                    Some(dir) if step(p, *dir) == prev => Some(p),
+                    _ => None,
+                }
+            });
+            match next {
+                Some(p) => {
+                    segments.remove(&p);
+                    body.push_back(p);
+                }
+                None => break,
+            }
+        }
+
+        let settings = snake_settings.remove(c).unwrap_or_default();
+        let length = settings.length.unwrap_or(body.len());
+        if length < body.len() {
+            return Err(format!(
+                "snake {} has {} segments, but a length of {}",
+                c,
+                body.len(),
+                length
+            ));
+        }
+        while body.len() < length {
+            body.push_back(*body.back().unwrap());
+        }
+
+        let name = settings.name.unwrap_or_else(|| c.to_string());
+        snakes.push(protocol::Snake {
+            id: c.to_string(),
+            name,
+            health: settings.health.unwrap_or(100),
+            body,
+            latency: "0".to_string(),
+            head: *head,
+            length,
+            shout: String::new(),
+            squad: settings.squad,
+            customizations: protocol::Customizations::default(),
+            extra: protocol::Extra::default(),
+        });
+    }
+    if let Some(p) = segments.keys().next() {
+        return Err(format!("segment at {} is not connected to a head", p));
+    }
+
+    // "you: -" plays as a snake that is no longer on the board
+    let you = match you {
+        Some('-') => dead_snake(),
+        Some(c) => snakes
+            .iter()
+            .find(|s| s.id == c.to_string())
+            .cloned()
+            .ok_or_else(|| format!("snake {} is not on the board", c))?,
+        None => snakes.first().cloned().unwrap_or_else(dead_snake),
+    };
+
+    Ok(Position {
+        request: protocol::Request {
+            game: protocol::Game {
+                id: "notation".to_string(),
+                ruleset,
+                map,
+                timeout,
+                source: String::new(),
+                extra: protocol::Extra::default(),
+            },
+            turn,
+            board: protocol::Board {
+                height: h,
+                width: w,
+                food,
+                hazards,
+                snakes,
+                extra: protocol::Extra::default(),
+            },
+            you,
+            extra: protocol::Extra::default(),
+        },
+        walls,
+        seed,
+    })
+}
+
+fn dead_snake() -> protocol::Snake {
+    protocol::Snake {
+        id: "-".to_string(),
+        name: "-".to_string(),
+        health: 0,
+        body: VecDeque::new(),
+        latency: "0".to_string(),
+        head: Point { x: -1, y: -1 },
+        length: 0,
+        shout: String::new(),
+        squad: String::new(),
+        customizations: protocol::Customizations::default(),
+        extra: protocol::Extra::default(),
+    }
+}
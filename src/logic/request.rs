@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+use crate::protocol;
+
+use super::{
+    game::{self, GameMode},
+    maps::MapKind,
+    Game, Point, Snake,
+};
+
+/// Metadata holds the parts of a request that the rules engine doesn't use,
+/// so a Game can be turned back into a protocol::Request.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Metadata {
+    pub game: protocol::Game,
+    // The snakes of the request indexed by Snake::id, without their bodies
+    pub snakes: Vec<protocol::Snake>,
+    // Position of you in the snakes on the board of the request
+    pub you_index: Option<usize>,
+    pub board_extra: protocol::Extra,
+    pub extra: protocol::Extra,
+}
+
+impl From<&protocol::Request> for Metadata {
+    fn from(req: &protocol::Request) -> Self {
+        let snakes = std::iter::once(&req.you)
+            .chain(req.board.snakes.iter().filter(|s| s.id != req.you.id))
+            .map(|s| protocol::Snake {
+                body: VecDeque::new(),
+                ..s.clone()
+            })
+            .collect();
+
+        Self {
+            game: req.game.clone(),
+            snakes,
+            you_index: req.board.snakes.iter().position(|s| s.id == req.you.id),
+            board_extra: req.board.extra.clone(),
+            extra: req.extra.clone(),
+        }
+    }
+}
+
+impl Game {
+    /// request_for builds the request the snake with the given id would
+    /// receive for the current state of the game, or None if there is no
+    /// such snake. Walls are not part of the protocol and are left out.
+    pub fn request_for(&self, id: u8) -> Option<protocol::Request> {
+        let snake = std::iter::once(&self.you)
+            .chain(self.others.iter())
+            .find(|s| s.id == id)?;
+
+        let mut snakes: Vec<protocol::Snake> = self
+            .others
+            .iter()
+            .filter(|s| !s.dead())
+            .map(|s| self.protocol_snake(s))
+            .collect();
+        if !self.you.dead() {
+            let idx = self.metadata.you_index.unwrap_or(0).min(snakes.len());
+            snakes.insert(idx, self.protocol_snake(&self.you));
+        }
+
+        let mut food = vec![];
+        let mut hazards = vec![];
+        for x in 0..self.board.width() as i8 {
+            for y in 0..self.board.height() as i8 {
+                let p = Point { x, y };
+                if self.board.get(&p).has_food() {
+                    food.push(p);
+                }
+                for _ in 0..self.board.hazard_count(&p) {
+                    hazards.push(p);
+                }
+            }
+        }
+
+        Some(protocol::Request {
+            game: protocol::Game {
+                ruleset: self.protocol_ruleset(),
+                map: self.map_name(),
+                timeout: self.timeout.as_millis() as isize,
+                ..self.metadata.game.clone()
+            },
+            turn: self.turn,
+            board: protocol::Board {
+                height: self.board.height() as usize,
+                width: self.board.width() as usize,
+                food,
+                hazards,
+                snakes,
+                extra: self.metadata.board_extra.clone(),
+            },
+            you: self.protocol_snake(snake),
+            extra: self.metadata.extra.clone(),
+        })
+    }
+
+    fn protocol_snake(&self, snake: &Snake) -> protocol::Snake {
+        let template = self.metadata.snakes.get(snake.id as usize);
+        protocol::Snake {
+            id: template.map_or_else(|| format!("snake-{}", snake.id), |t| t.id.clone()),
+            name: template.map_or_else(|| format!("snake-{}", snake.id), |t| t.name.clone()),
+            health: snake.health as isize,
+            body: snake.body.clone(),
+            latency: template.map(|t| t.latency.clone()).unwrap_or_default(),
+            head: snake.head,
+            length: snake.length,
+            shout: template.map(|t| t.shout.clone()).unwrap_or_default(),
+            squad: template.map(|t| t.squad.clone()).unwrap_or_default(),
+            customizations: template
+                .map(|t| t.customizations.clone())
+                .unwrap_or_default(),
+            extra: template.map(|t| t.extra.clone()).unwrap_or_default(),
+        }
+    }
+
+    // The original ruleset, updated with any changes made to the rules
+    fn protocol_ruleset(&self) -> protocol::Ruleset {
+        let mut ruleset = self.metadata.game.ruleset.clone();
+        if GameMode::from_name(&ruleset.name) != Some(self.rules.game_mode) {
+            ruleset.name = self.rules.game_mode.name().to_string();
+        }
+
+        let settings = &mut ruleset.settings;
+        settings.food_spawn_chance = self.rules.food_spawn_chance as usize;
+        settings.minimum_food = self.rules.minimum_food as usize;
+        settings.hazard_damage_per_turn = self.rules.hazard_damage_per_turn as isize;
+        if self.rules.shrink_every_n_turns != 0 {
+            settings.royale.shrink_every_n_turns = self.rules.shrink_every_n_turns;
+        }
+        if self.rules.game_mode == GameMode::Squad {
+            settings.squad.allow_body_collisions = self.rules.squad.allow_body_collisions;
+            settings.squad.shared_elimination = self.rules.squad.shared_elimination;
+            settings.squad.shared_health = self.rules.squad.shared_health;
+            settings.squad.shared_length = self.rules.squad.shared_length;
+        }
+        ruleset
+    }
+
+    // The original map name, unless the map was changed
+    fn map_name(&self) -> String {
+        let original = &self.metadata.game.map;
+        // Game::try_from plays unknown maps as the standard map
+        let map = game::map_kind(self.rules.game_mode, original).unwrap_or(MapKind::Standard);
+        if map == self.map {
+            original.clone()
+        } else {
+            self.map.get().name().to_string()
+        }
+    }
+}
+
+impl From<&Game> for protocol::Request {
+    fn from(game: &Game) -> Self {
+        game.request_for(game.you.id).unwrap()
+    }
+}
@@ -0,0 +1,158 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{Board, Direction, Point};
+
+/// SafeZone is the rectangle of a royale board that is not yet covered
+/// in hazards. All bounds are inclusive.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct SafeZone {
+    pub min_x: i8,
+    pub max_x: i8,
+    pub min_y: i8,
+    pub max_y: i8,
+}
+
+impl SafeZone {
+    pub fn new(width: isize, height: isize) -> Self {
+        Self {
+            min_x: 0,
+            max_x: width as i8 - 1,
+            min_y: 0,
+            max_y: height as i8 - 1,
+        }
+    }
+
+    /// from_board reconstructs the safe zone from the hazards on the board,
+    /// as the smallest rectangle that contains every hazard free tile.
+    pub fn from_board(board: &Board) -> Self {
+        let mut zone: Option<SafeZone> = None;
+        for x in 0..board.width() as i8 {
+            for y in 0..board.height() as i8 {
+                if board.hazard_count(&Point { x, y }) > 0 {
+                    continue;
+                }
+                zone = Some(match zone {
+                    None => SafeZone {
+                        min_x: x,
+                        max_x: x,
+                        min_y: y,
+                        max_y: y,
+                    },
+                    Some(z) => SafeZone {
+                        min_x: z.min_x.min(x),
+                        max_x: z.max_x.max(x),
+                        min_y: z.min_y.min(y),
+                        max_y: z.max_y.max(y),
+                    },
+                });
+            }
+        }
+
+        // A board that is completely covered can't shrink any further,
+        // any single tile works as the zone.
+        zone.unwrap_or(SafeZone {
+            min_x: 0,
+            max_x: 0,
+            min_y: 0,
+            max_y: 0,
+        })
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, p: &Point) -> bool {
+        p.x >= self.min_x && p.x <= self.max_x && p.y >= self.min_y && p.y <= self.max_y
+    }
+
+    /// shrink covers one edge of the zone in hazards. The hazards grow from
+    /// the board edge in the given direction: shrinking Left moves the left
+    /// boundary of the zone one tile to the right. The zone never shrinks
+    /// below a single column or row.
+    pub fn shrink(&mut self, side: Direction) {
+        match side {
+            Direction::Left => {
+                if self.min_x < self.max_x {
+                    self.min_x += 1
+                }
+            }
+            Direction::Right => {
+                if self.max_x > self.min_x {
+                    self.max_x -= 1
+                }
+            }
+            Direction::Down => {
+                if self.min_y < self.max_y {
+                    self.min_y += 1
+                }
+            }
+            Direction::Up => {
+                if self.max_y > self.min_y {
+                    self.max_y -= 1
+                }
+            }
+        }
+    }
+
+    /// predicted_side guesses which side shrinks next when the game's seed
+    /// is unknown. It shrinks the longest axis of the zone, from the side that
+    /// is furthest away from the center of the board, so the prediction keeps
+    /// the zone as large and as central as possible.
+    pub fn predicted_side(&self, width: isize, height: isize) -> Direction {
+        let (center_x, center_y) = ((width as i8 - 1) / 2, (height as i8 - 1) / 2);
+        if self.max_x - self.min_x > self.max_y - self.min_y {
+            if self.max_x - center_x > center_x - self.min_x {
+                Direction::Right
+            } else {
+                Direction::Left
+            }
+        } else if self.max_y - center_y > center_y - self.min_y {
+            Direction::Up
+        } else {
+            Direction::Down
+        }
+    }
+
+    pub fn hazards(&self, width: isize, height: isize) -> Vec<Point> {
+        let mut res = vec![];
+        for x in 0..width as i8 {
+            for y in 0..height as i8 {
+                let p = Point { x, y };
+                if !self.contains(&p) {
+                    res.push(p);
+                }
+            }
+        }
+        res
+    }
+}
+
+pub fn shrink_count(turn: usize, shrink_every_n_turns: usize) -> usize {
+    turn.checked_div(shrink_every_n_turns).unwrap_or(0)
+}
+
+/// shrink_sides generates the order in which the sides of the board are
+/// covered in hazards. Like the official rules, a single random sequence
+/// is drawn from the game's seed, so the zone at any turn can be derived.
+pub fn shrink_sides(seed: u64, count: usize) -> impl Iterator<Item = Direction> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count).map(move |_| match rng.gen_range(0..4) {
+        0 => Direction::Left,
+        1 => Direction::Right,
+        2 => Direction::Down,
+        _ => Direction::Up,
+    })
+}
+
+/// safe_zone calculates the zone for a given turn of a game with a known seed.
+pub fn safe_zone(
+    width: isize,
+    height: isize,
+    turn: usize,
+    shrink_every_n_turns: usize,
+    seed: u64,
+) -> SafeZone {
+    let mut zone = SafeZone::new(width, height);
+    for side in shrink_sides(seed, shrink_count(turn, shrink_every_n_turns)) {
+        zone.shrink(side);
+    }
+    zone
+}
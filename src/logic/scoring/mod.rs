@@ -20,6 +20,69 @@ where
     }
 }
 
+/// MultiScorer scores a game for every snake at once, for searches where
+/// every snake picks the move that is best for itself instead of the move
+/// that is worst for us.
+pub trait MultiScorer {
+    /// scores returns a score for every snake, indexed by Snake::id.
+    /// Eliminated snakes may be missing from the end.
+    fn scores(&self, game: &Game) -> Vec<i64>;
+
+    /// max_sum is an upper bound for the sum of all scores, if the scores
+    /// are never negative. Searches use it to prune.
+    fn max_sum(&self, _game: &Game) -> Option<i64> {
+        None
+    }
+}
+
+/// score_of looks up the score of a snake, eliminated snakes that are
+/// missing from the scores get the lowest score possible.
+pub fn score_of(scores: &[i64], id: u8) -> i64 {
+    scores.get(id as usize).copied().unwrap_or(i64::MIN)
+}
+
+/// Perspectives scores the game with a Scorer for every snake, by looking at
+/// the game from the point of view of each snake in turn.
+#[derive(Clone)]
+pub struct Perspectives<S: Scorer>(pub S);
+
+impl<S: Scorer> MultiScorer for Perspectives<S> {
+    fn scores(&self, game: &Game) -> Vec<i64> {
+        let snakes = std::iter::once(&game.you).chain(game.others.iter());
+        let mut res = vec![i64::MIN; snakes.clone().map(|s| s.id as usize + 1).max().unwrap()];
+        for snake in snakes {
+            if let Some(seen) = game.seen_by(snake.id) {
+                res[snake.id as usize] = self.0.score(&seen);
+            }
+        }
+        res
+    }
+}
+
+/// TileShares scores every snake by the number of tiles it reaches before
+/// the others do. Dead snakes get nothing, and the scores never add up to
+/// more than the number of tiles without walls.
+#[derive(Clone, Copy)]
+pub struct TileShares;
+
+impl MultiScorer for TileShares {
+    fn scores(&self, game: &Game) -> Vec<i64> {
+        let tiles = super::floodfill::all(game);
+        let mut res = vec![0; tiles.keys().map(|s| s.id as usize + 1).max().unwrap()];
+        for (snake, count) in tiles {
+            if !snake.dead() {
+                res[snake.id as usize] = count as i64;
+            }
+        }
+        res
+    }
+
+    fn max_sum(&self, game: &Game) -> Option<i64> {
+        let tiles = game.board.width() * game.board.height();
+        Some(tiles as i64 - game.board.bits().walls.count() as i64)
+    }
+}
+
 #[derive(Copy, Ord, Clone, PartialEq, Eq, Default)]
 pub struct SurvivalKillsLengthScore {
     turns_survived: i64,
@@ -1,11 +1,14 @@
-use std::{cmp, mem::MaybeUninit};
+use std::{
+    cmp,
+    ops::{Deref, DerefMut},
+};
 
 use crate::{
     logic::{game::GameMode, Game, Point, Tile},
     util::stackqueue::StackDequeue,
 };
 
-use super::{NumType, SnakeScore, MAX_SNAKES, NO_SNAKE};
+use super::{NumType, SnakeScore, NO_SNAKE};
 
 #[derive(Copy, Clone)]
 struct TileInfo {
@@ -15,6 +18,7 @@ struct TileInfo {
     food: bool,
     hazard_count: i8,
     snake: u8,
+    body_snake: u8,
 }
 
 #[derive(Default, Copy, Clone)]
@@ -26,89 +30,185 @@ struct Work {
     health: i8,
 }
 
-const MAX_WIDTH: usize = 25;
-const MAX_HEIGHT: usize = 25;
+const UNCLAIMED: TileInfo = TileInfo {
+    snake_length: 0,
+    snake_distance: NumType::MAX,
+    inaccessible_turns: 0,
+    hazard_count: 0,
+    snake: NO_SNAKE,
+    body_snake: NO_SNAKE,
+    food: false,
+};
+
+// Boards up to this size are filled on the stack
+const FAST_PATH_TILES: usize = 25 * 25;
+// Games with up to this many snakes are scored without allocating
+const FAST_PATH_SNAKES: usize = 8;
 
-pub fn floodfill<const MAX_DISTANCE: NumType>(game: &Game) -> [SnakeScore; MAX_SNAKES] {
+// Slots holds count values, on the stack if they fit in N.
+enum Slots<T: Copy, const N: usize> {
+    Inline(usize, [T; N]),
+    Heap(Vec<T>),
+}
+
+impl<T: Copy, const N: usize> Slots<T, N> {
+    fn new(count: usize, value: T) -> Self {
+        if count <= N {
+            Slots::Inline(count, [value; N])
+        } else {
+            Slots::Heap(vec![value; count])
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> Deref for Slots<T, N> {
+    type Target = [T];
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Slots::Inline(count, values) => &values[..*count],
+            Slots::Heap(values) => values,
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> DerefMut for Slots<T, N> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Slots::Inline(count, values) => &mut values[..*count],
+            Slots::Heap(values) => values,
+        }
+    }
+}
+
+/// Scores holds the SnakeScore of every snake, and the distance at which
+/// every pair of snakes can collide.
+pub struct Scores {
+    snakes: Slots<SnakeScore, FAST_PATH_SNAKES>,
+    collisions: Slots<NumType, { FAST_PATH_SNAKES * FAST_PATH_SNAKES }>,
+}
+
+impl Scores {
+    fn new(count: usize) -> Self {
+        let mut collisions = Slots::new(count * count, NumType::MAX);
+        for i in 0..count {
+            collisions[i * count + i] = 0;
+        }
+        let score = SnakeScore {
+            food_count: 0,
+            tile_count: 0,
+            hazard_count: 0,
+            food_distance: NumType::MAX,
+            food_at_min_distance: 0,
+        };
+        Self {
+            snakes: Slots::new(count, score),
+            collisions,
+        }
+    }
+
+    pub fn distance_to_collision(&self, a: usize, b: usize) -> NumType {
+        self.collisions[a * self.snakes.len() + b]
+    }
+
+    fn update_collision(&mut self, a: usize, b: usize, distance: NumType) {
+        let count = self.snakes.len();
+        if self.collisions[a * count + b] > distance {
+            self.collisions[a * count + b] = distance;
+            self.collisions[b * count + a] = distance;
+        }
+    }
+}
+
+impl Deref for Scores {
+    type Target = [SnakeScore];
+
+    fn deref(&self) -> &Self::Target {
+        &self.snakes
+    }
+}
+
+pub fn floodfill<const MAX_DISTANCE: NumType>(game: &Game) -> Scores {
+    let tiles = (game.board.width() * game.board.height()) as usize;
+    if tiles <= FAST_PATH_TILES {
+        fill::<MAX_DISTANCE>(game, &mut [UNCLAIMED; FAST_PATH_TILES])
+    } else {
+        fill::<MAX_DISTANCE>(game, &mut vec![UNCLAIMED; tiles])
+    }
+}
+
+fn fill<const MAX_DISTANCE: NumType>(game: &Game, board: &mut [TileInfo]) -> Scores {
     let constrictor = game.rules.game_mode == GameMode::Constrictor;
     let warp = game.rules.game_mode == GameMode::Wrapped;
     let hazard_damage = game.rules.hazard_damage_per_turn;
 
     let mut queue: StackDequeue<Work, 256> = StackDequeue::new();
-    let mut board = [[TileInfo {
-        snake_length: 0,
-        snake_distance: NumType::MAX,
-        inaccessible_turns: 0,
-        hazard_count: 0,
-        snake: NO_SNAKE,
-        food: false,
-    }; MAX_HEIGHT]; MAX_WIDTH];
-
-    let mut scores = {
-        // Create an array of uninitialized values.
-        let mut array: [MaybeUninit<SnakeScore>; MAX_SNAKES] =
-            unsafe { MaybeUninit::uninit().assume_init() };
-
-        for element in array.iter_mut() {
-            *element = MaybeUninit::new(SnakeScore {
-                food_count: 0,
-                tile_count: 0,
-                hazard_count: 0,
-                food_distance: NumType::MAX,
-                food_at_min_distance: 0,
-                distance_to_collision: [NumType::MAX; MAX_SNAKES],
-            });
-        }
 
-        unsafe { std::mem::transmute::<_, [SnakeScore; MAX_SNAKES]>(array) }
+    // Teammates don't compete for tiles, and can cross each other's bodies
+    // when the rules allow it.
+    let squad = |snake: u8| match snake {
+        0 => Some(game.you.squad),
+        _ => game.others.get(snake as usize - 1).map(|s| s.squad),
     };
-    for i in 0..scores.len() {
-        scores[i].distance_to_collision[i] = 0;
-    }
+    let allies = |a: u8, b: u8| a != b && squad(a).is_some_and(|s| s != 0 && squad(b) == Some(s));
+    let body_collisions = game.rules.squad.allow_body_collisions;
+
+    let mut scores = Scores::new(game.others.len() + 1);
 
     let (w, h) = (game.board.width() as usize, game.board.height() as usize);
 
     for x in 0..w {
         for y in 0..h {
+            let i = x + y * w;
             let hazards = match game.board.get(&Point {
                 x: x as i8,
                 y: y as i8,
             }) {
                 Tile::Food => {
-                    board[x][y].food = true;
+                    board[i].food = true;
                     0
                 }
                 Tile::Hazard(hazards)
                 | Tile::HazardWithSnake(hazards)
                 | Tile::HazardWithHead(hazards) => hazards,
                 Tile::HazardWithFood(hazards) => {
-                    board[x][y].food = true;
+                    board[i].food = true;
                     hazards
                 }
+                Tile::Wall => {
+                    board[i].inaccessible_turns = NumType::MAX;
+                    0
+                }
                 _ => 0,
             };
 
-            board[x][y].hazard_count = hazards as i8;
+            board[i].hazard_count = hazards as i8;
         }
     }
 
     for (idx, p) in game.you.body.iter().enumerate() {
-        let (x, y) = (p.x as usize, p.y as usize);
+        let i = p.x as usize + p.y as usize * w;
         let present_for_turns = if constrictor {
             NumType::MAX
         } else {
             (game.you.length - idx - 1) as NumType
         };
-        board[x][y].inaccessible_turns =
-            cmp::max(board[x][y].inaccessible_turns, present_for_turns);
+        if present_for_turns >= board[i].inaccessible_turns {
+            board[i].inaccessible_turns = present_for_turns;
+            board[i].body_snake = 0;
+        }
     }
 
-    for snake in &game.others {
+    for (n, snake) in game.others.iter().enumerate() {
         for (idx, p) in snake.body.iter().enumerate() {
-            let (x, y) = (p.x as usize, p.y as usize);
+            let i = p.x as usize + p.y as usize * w;
             let present_for_turns = (snake.length - idx - 1) as NumType;
-            board[x][y].inaccessible_turns =
-                cmp::max(board[x][y].inaccessible_turns, present_for_turns);
+            if present_for_turns >= board[i].inaccessible_turns {
+                board[i].inaccessible_turns = present_for_turns;
+                board[i].body_snake = n as u8 + 1;
+            }
         }
     }
 
@@ -130,76 +230,77 @@ pub fn floodfill<const MAX_DISTANCE: NumType>(game: &Game) -> [SnakeScore; MAX_S
     }
 
     while let Some(work) = queue.pop_front() {
-        let (x, y) = (work.p.x as usize, work.p.y as usize);
+        let i = work.p.x as usize + work.p.y as usize * w;
 
-        if board[x][y].snake != NO_SNAKE
-            && board[x][y].snake != work.snake
-            && board[x][y].snake_distance == work.snake_distance
-            && board[x][y].snake_length == work.snake_length
+        if board[i].snake != NO_SNAKE && allies(board[i].snake, work.snake) {
+            // Already claimed by a teammate
+            continue;
+        } else if board[i].snake != NO_SNAKE
+            && board[i].snake != work.snake
+            && board[i].snake_distance == work.snake_distance
+            && board[i].snake_length == work.snake_length
         {
             // Draw: no snake gets this tile, unmark the current snake & decrease its score
-            let snake = board[x][y].snake as usize;
-            let work_snake = work.snake as usize;
-            if scores[work_snake].distance_to_collision[snake] > work.snake_distance {
-                scores[work_snake].distance_to_collision[snake] = work.snake_distance;
-                scores[snake].distance_to_collision[work_snake] = work.snake_distance;
-            }
+            let snake = board[i].snake as usize;
+            scores.update_collision(work.snake as usize, snake, work.snake_distance);
 
-            scores[snake].tile_count -= 1;
-            scores[snake].hazard_count -= board[x][y].hazard_count as NumType;
-            if board[x][y].food {
-                scores[snake].food_count -= 1;
-                if scores[snake].food_distance == work.snake_distance {
-                    scores[snake].food_at_min_distance -= 1;
-                    if scores[snake].food_at_min_distance == 0 {
-                        scores[snake].food_distance = NumType::MAX;
+            scores.snakes[snake].tile_count -= 1;
+            scores.snakes[snake].hazard_count -= board[i].hazard_count as u32;
+            if board[i].food {
+                scores.snakes[snake].food_count -= 1;
+                if scores.snakes[snake].food_distance == work.snake_distance {
+                    scores.snakes[snake].food_at_min_distance -= 1;
+                    if scores.snakes[snake].food_at_min_distance == 0 {
+                        scores.snakes[snake].food_distance = NumType::MAX;
                     }
                 }
             }
-            board[x][y].snake = NO_SNAKE;
-        } else if board[x][y].snake_distance > work.snake_distance
-            || (board[x][y].snake_distance == work.snake_distance
-                && board[x][y].snake_length < work.snake_length)
+            board[i].snake = NO_SNAKE;
+        } else if board[i].snake_distance > work.snake_distance
+            || (board[i].snake_distance == work.snake_distance
+                && board[i].snake_length < work.snake_length)
         {
             // We're first!
-            if board[x][y].snake != NO_SNAKE {
+            if board[i].snake != NO_SNAKE {
                 // Remove score from previous snake if there is one
-                let snake = board[x][y].snake as usize;
-                scores[snake].tile_count -= 1;
-                scores[snake].hazard_count -= board[x][y].hazard_count as NumType;
-                if board[x][y].food {
-                    scores[snake].food_count -= 1;
-                    if scores[snake].food_distance == work.snake_distance {
-                        scores[snake].food_at_min_distance -= 1;
-                        if scores[snake].food_at_min_distance == 0 {
-                            scores[snake].food_distance = NumType::MAX;
+                let snake = board[i].snake as usize;
+                scores.snakes[snake].tile_count -= 1;
+                scores.snakes[snake].hazard_count -= board[i].hazard_count as u32;
+                if board[i].food {
+                    scores.snakes[snake].food_count -= 1;
+                    if scores.snakes[snake].food_distance == work.snake_distance {
+                        scores.snakes[snake].food_at_min_distance -= 1;
+                        if scores.snakes[snake].food_at_min_distance == 0 {
+                            scores.snakes[snake].food_distance = NumType::MAX;
                         }
                     }
                 }
-                board[x][y].snake = NO_SNAKE;
+                board[i].snake = NO_SNAKE;
             }
 
-            scores[work.snake as usize].tile_count += 1;
-            scores[work.snake as usize].hazard_count += board[x][y].hazard_count as NumType;
-            if board[x][y].food {
-                scores[work.snake as usize].food_count += 1;
-                if scores[work.snake as usize].food_distance > work.snake_distance {
-                    scores[work.snake as usize].food_distance = work.snake_distance;
-                    scores[work.snake as usize].food_at_min_distance = 1;
-                } else if scores[work.snake as usize].food_distance > work.snake_distance {
-                    scores[work.snake as usize].food_at_min_distance += 1;
+            scores.snakes[work.snake as usize].tile_count += 1;
+            scores.snakes[work.snake as usize].hazard_count += board[i].hazard_count as u32;
+            if board[i].food {
+                scores.snakes[work.snake as usize].food_count += 1;
+                if scores.snakes[work.snake as usize].food_distance > work.snake_distance {
+                    scores.snakes[work.snake as usize].food_distance = work.snake_distance;
+                    scores.snakes[work.snake as usize].food_at_min_distance = 1;
+                } else if scores.snakes[work.snake as usize].food_distance > work.snake_distance {
+                    scores.snakes[work.snake as usize].food_at_min_distance += 1;
                 }
             }
 
             // Update the board
-            board[x][y].snake = work.snake;
-            board[x][y].snake_distance = work.snake_distance;
-            board[x][y].snake_length = work.snake_length;
+            board[i].snake = work.snake;
+            board[i].snake_distance = work.snake_distance;
+            board[i].snake_length = work.snake_length;
 
-            let next_health = if board[x][y].food {
+            let next_health = if board[i].food {
                 100
             } else {
-                work.health - board[x][y].hazard_count * hazard_damage - 1
+                work.health
+                    .saturating_sub(board[i].hazard_count.saturating_mul(hazard_damage))
+                    - 1
             };
 
             // Enqueue neighbouring tiles
@@ -213,9 +314,9 @@ pub fn floodfill<const MAX_DISTANCE: NumType>(game: &Game) -> [SnakeScore; MAX_S
                     continue;
                 }
 
-                let (x, y) = (next_p.x as usize, next_p.y as usize);
+                let i = next_p.x as usize + next_p.y as usize * w;
                 let next_work = Work {
-                    snake_length: if board[x][y].food {
+                    snake_length: if board[i].food {
                         work.snake_length + 1
                     } else {
                         work.snake_length
@@ -226,29 +327,31 @@ pub fn floodfill<const MAX_DISTANCE: NumType>(game: &Game) -> [SnakeScore; MAX_S
                     health: next_health,
                 };
 
-                let damage = if !board[x][y].food {
-                    board[x][y].hazard_count * hazard_damage + 1
+                let damage = if !board[i].food {
+                    board[i]
+                        .hazard_count
+                        .saturating_mul(hazard_damage)
+                        .saturating_add(1)
                 } else {
                     0
                 };
 
+                let collision = board[i].inaccessible_turns >= next_work.snake_distance
+                    && !(body_collisions && allies(work.snake, board[i].body_snake));
+
                 if damage >= next_work.health // snake starves or is killed by hazard
-                    || board[x][y].inaccessible_turns >= next_work.snake_distance // colission
+                    || collision
                     || next_work.snake_distance >= MAX_DISTANCE
                 // reached max traveling distance
                 {
                     continue;
                 }
 
-                if next_work.snake_distance > board[x][y].snake_distance {
+                if next_work.snake_distance > board[i].snake_distance {
                     // someone else got there first
-                    if board[x][y].snake != NO_SNAKE {
-                        let snake = board[x][y].snake as usize;
-                        let work_snake = work.snake as usize;
-                        if scores[work_snake].distance_to_collision[snake] > work.snake_distance {
-                            scores[work_snake].distance_to_collision[snake] = work.snake_distance;
-                            scores[snake].distance_to_collision[work_snake] = work.snake_distance;
-                        }
+                    if board[i].snake != NO_SNAKE && !allies(board[i].snake, work.snake) {
+                        let snake = board[i].snake as usize;
+                        scores.update_collision(work.snake as usize, snake, work.snake_distance);
                     }
                     continue;
                 }
@@ -269,8 +372,8 @@ pub fn floodfill<const MAX_DISTANCE: NumType>(game: &Game) -> [SnakeScore; MAX_S
                     y: y as i8,
                 });
                 match tile {
-                    crate::logic::Tile::Head => print!("<{:03}>", board[x][y].snake),
-                    _ => print!(" {:03} ", board[x][y].snake),
+                    crate::logic::Tile::Head => print!("<{:03}>", board[x + y * w].snake),
+                    _ => print!(" {:03} ", board[x + y * w].snake),
                 }
             }
             println!();
@@ -11,24 +11,22 @@ use crate::{
 
 const ENCODING: &str = include_str!("encoding.txt");
 
-pub use self::floodfill::floodfill;
+pub use self::floodfill::{floodfill, Scores};
 
 use super::Scorer;
 
 mod floodfill;
 
-pub type NumType = u8;
+pub type NumType = u16;
 pub const NO_SNAKE: u8 = u8::MAX;
-const MAX_SNAKES: usize = 12;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct SnakeScore {
     pub food_count: NumType,
     pub tile_count: NumType,
-    pub hazard_count: NumType,
+    pub hazard_count: u32,
     pub food_distance: NumType,
     pub food_at_min_distance: NumType,
-    pub distance_to_collision: [NumType; MAX_SNAKES],
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -295,6 +293,9 @@ impl<const MAX_DISTANCE: NumType> Scorer for Config<MAX_DISTANCE> {
 
         let mut length_rank = 0;
         for (i, snake) in game.others.iter().enumerate() {
+            if snake.same_squad(&game.you) {
+                continue;
+            }
             score += self.points_per_length_diff as i64
                 * cmp::min(
                     self.length_diff_cap as i64,
@@ -308,7 +309,7 @@ impl<const MAX_DISTANCE: NumType> Scorer for Config<MAX_DISTANCE> {
             } else {
                 score += self.points_per_distance_to_smaller_enemies as i64
                     * cmp::min(
-                        flood_info[0].distance_to_collision[i + 1],
+                        flood_info.distance_to_collision(0, i + 1),
                         self.enemy_distance_cap as NumType,
                     ) as i64;
             }
@@ -1,11 +1,11 @@
-use std::{cmp, collections::VecDeque};
+use std::collections::VecDeque;
 
 use crate::protocol;
 
 use super::{
-    board,
     game::{GameMode, Rules},
-    Board, Direction, Point, Tile,
+    maps::Map,
+    zobrist, Board, Direction, Point, Tile,
 };
 
 #[derive(Clone, Hash, Eq)]
@@ -18,6 +18,8 @@ pub struct Snake {
     pub head: Point,
     pub length: usize,
     pub squad: u8,
+    // Zobrist hash of the body, see zobrist::body_hash()
+    pub hash: u64,
 }
 
 impl PartialEq for Snake {
@@ -32,8 +34,8 @@ impl Snake {
         dir: Direction,
         board: &mut Board,
         rules: &Rules,
-        snail_mode: bool,
-    ) {
+        map: &dyn Map,
+    ) -> Option<Point> {
         let mut new_head = self.head.neighbour(dir);
         if rules.game_mode == GameMode::Wrapped {
             new_head.warp(board.width(), board.height())
@@ -56,20 +58,28 @@ impl Snake {
             board.clear_snake(&self.head);
         }
 
+        self.hash ^= zobrist::head_key(&self.head) ^ zobrist::head_key(&new_head);
+        if self.head != new_head {
+            self.hash ^= zobrist::segment_key(&self.head, &new_head);
+        }
         self.head = new_head.clone();
         self.body.push_front(new_head.clone());
+        let mut tail = None;
         if self.body.len() > self.length {
-            if let Some(p) = self.body.pop_back() {
+            tail = self.body.pop_back();
+            if let Some(p) = tail {
                 let clear_tail = if let Some(p2) = self.body.back() {
                     &p != p2
                 } else {
                     true
                 };
                 if clear_tail {
+                    if let Some(prev) = self.body.back() {
+                        self.hash ^= zobrist::segment_key(&p, prev);
+                    }
                     board.clear_snake(&p);
-                    if snail_mode {
-                        let extra_hazards =
-                            cmp::min(self.length, board::MAX_HAZARDS as usize) as u8;
+                    let extra_hazards = map.tail_hazards(self);
+                    if extra_hazards > 0 {
                         board.add(&p, Tile::Hazard(extra_hazards))
                     }
                 }
@@ -83,10 +93,18 @@ impl Snake {
             self.body.push_back(self.body.back().unwrap().clone())
         }
 
-        // Apply out of bounds damage
-        if new_head.out_of_bounds(board.width(), board.height()) {
+        // Apply out of bounds damage, walls are just as deadly
+        if board.get(&new_head) == Tile::Wall {
             self.health = 0;
         }
+
+        tail
+    }
+
+    /// same_squad is true for teammates, a snake is not its own teammate.
+    #[inline(always)]
+    pub fn same_squad(&self, other: &Snake) -> bool {
+        self.squad != 0 && self.squad == other.squad && self.id != other.id
     }
 
     #[inline(always)]
@@ -113,7 +131,9 @@ impl From<&protocol::Snake> for Snake {
             body: s.body.clone(),
             head: s.head.clone(),
             length: s.length,
-            squad: s.squad.parse().unwrap_or(0),
+            // Squad names are numbered by Game::try_from
+            squad: 0,
+            hash: zobrist::body_hash(s.body.iter()),
         }
     }
 }
@@ -0,0 +1,164 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    logic::{
+        bitboard::{self, Bitboard, Grid},
+        floodfill,
+        notation::parse_game,
+        Board, Direction, Game, Point, Tile,
+    },
+    protocol::{self, ALL_DIRECTIONS},
+};
+
+fn all_points(w: isize, h: isize) -> impl Iterator<Item = Point> {
+    (0..h as i8).flat_map(move |y| (0..w as i8).map(move |x| Point { x, y }))
+}
+
+fn assert_bits_match(board: &Board) {
+    let bits = board.bits();
+    for p in all_points(board.width(), board.height()) {
+        let t = board.get(&p);
+        assert_eq!(bits.snakes.get(&p), t.is_snake(), "snake at {}", p);
+        assert_eq!(bits.heads.get(&p), board.is_head(&p), "head at {}", p);
+        assert_eq!(bits.food.get(&p), t.has_food(), "food at {}", p);
+        assert_eq!(bits.hazards.get(&p), t.is_hazard(), "hazard at {}", p);
+        assert_eq!(bits.walls.get(&p), t == Tile::Wall, "wall at {}", p);
+    }
+}
+
+#[test]
+fn bits_follow_board() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/wrapped_rivers_and_lakes_opening.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+    assert_bits_match(&game.board);
+
+    let others = vec![Direction::Up; game.others.len()];
+    let before = game.clone();
+    let undo = game.make_move(Direction::Left, &others);
+    assert_bits_match(&game.board);
+    game.unmake_move(undo);
+    assert_bits_match(&game.board);
+    assert!(game.board.bits().snakes == before.board.bits().snakes);
+
+    game.board.set(&Point { x: 1, y: 1 }, Tile::Wall);
+    game.board.remove_hazards(&Point { x: 0, y: 0 }, 1);
+    assert_bits_match(&game.board);
+}
+
+#[test]
+fn shifts_match_neighbours() {
+    let mut rng = StdRng::seed_from_u64(1);
+    // Single word, u128 and heap allocated boards
+    for (w, h) in [(7, 7), (11, 11), (19, 21), (25, 25), (1, 1), (127, 3), (127, 9)] {
+        for wrapped in [false, true] {
+            let grid = Grid::new(w, h, wrapped);
+            let mut b = Bitboard::new(w, h);
+            for p in all_points(w, h) {
+                b.set(&p, rng.gen_bool(0.3));
+            }
+
+            for dir in ALL_DIRECTIONS {
+                let shifted = grid.shift(&b, dir);
+                let mut expected = Bitboard::new(w, h);
+                for p in b.points() {
+                    let mut n = p.neighbour(dir);
+                    if wrapped {
+                        n.warp(w, h);
+                    }
+                    expected.set(&n, true);
+                }
+                assert!(shifted == expected, "{}x{} {} {}", w, h, wrapped, dir);
+            }
+            assert_eq!((!&b).count(), (w * h) as usize - b.count());
+        }
+    }
+}
+
+#[test]
+fn reachable_tiles() {
+    let game = parse_game(
+        "
+A . # . .
+^ . # . .
+^ # # . .
+. . . . .
+",
+    )
+    .unwrap();
+    let grid = Grid::for_game(&game);
+    let bits = game.board.bits();
+    let mut from = Bitboard::new(5, 4);
+    from.set(&Point { x: 1, y: 3 }, true);
+    let passable = !&(&bits.snakes | &bits.walls);
+
+    assert_eq!(grid.reachable(&from, &passable, 1).count(), 2);
+    assert_eq!(grid.reachable(&from, &passable, 2).count(), 2);
+
+    from = Bitboard::new(5, 4);
+    from.set(&Point { x: 0, y: 0 }, true);
+    let all = grid.reachable(&from, &passable, usize::MAX);
+    assert_eq!(all.count(), 11);
+    assert!(!all.get(&Point { x: 1, y: 2 }));
+}
+
+#[test]
+fn safe_moves() {
+    let game = parse_game(
+        "
+ruleset: wrapped
+B: length=3
+. . . .
+. . v .
+. . B .
+A < < .
+",
+    )
+    .unwrap();
+    let blocked = bitboard::blocked(&game);
+    let sorted = |mut moves: Vec<Direction>| {
+        moves.sort_by_key(|d| d.to_string());
+        moves
+    };
+    // Down and Left wrap around the board
+    let moves = bitboard::safe_moves(&game, &game.you, &blocked);
+    assert_eq!(
+        sorted(moves),
+        vec![Direction::Down, Direction::Left, Direction::Up]
+    );
+
+    // The tail of A moves out of the way, the stacked tail of B doesn't
+    let moves = bitboard::safe_moves(&game, &game.others[0], &blocked);
+    assert_eq!(
+        sorted(moves),
+        vec![Direction::Down, Direction::Left, Direction::Right]
+    );
+}
+
+#[test]
+fn voronoi_draws() {
+    // Equal snakes split the board, the middle column goes to nobody
+    let game = parse_game(
+        "
+. . . . .
+A . . . B
+^ . . . ^
+",
+    )
+    .unwrap();
+    assert_eq!(floodfill::me(&game), 5);
+    assert_eq!(floodfill::me_range_limit(&game, 2), 3);
+
+    // Longer snakes win the tiles they reach at the same time
+    let game = parse_game(
+        "
+B: length=3
+. . . . .
+A . . . B
+^ . . . ^
+",
+    )
+    .unwrap();
+    assert_eq!(floodfill::me(&game), 5);
+    assert_eq!(floodfill::all(&game)[&game.others[0]], 8);
+}
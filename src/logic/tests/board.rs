@@ -33,6 +33,7 @@ fn board_set() {
         Tile::HazardWithHead(1),
         Tile::HazardWithHead(2),
         Tile::HazardWithHead(3),
+        Tile::Wall,
     ] {
         let mut b = Board::new(11, 11);
         assert_eq!(b.width(), 11);
@@ -203,3 +204,30 @@ fn board_add_hazards() {
         }
     }
 }
+
+#[test]
+fn board_walls() {
+    let mut b = Board::new(5, 5);
+    let wall = &Point { x: 1, y: 1 };
+    let hazards = &Point { x: 2, y: 2 };
+    b.set(wall, Tile::Wall);
+    b.add(hazards, Tile::Hazard(MAX_HAZARDS));
+    b.add(hazards, Tile::Hazard(5));
+
+    assert_eq!(b.get(wall), Tile::Wall);
+    assert!(b.is_wall(wall));
+    assert_eq!(b.get(hazards), Tile::Hazard(MAX_HAZARDS));
+    assert!(!b.is_wall(hazards));
+    assert_eq!(b.damage(wall, 1), i8::MAX);
+    assert_eq!(b.damage(hazards, 1), MAX_HAZARDS as i8);
+    assert_eq!(b.damage(hazards, 100), i8::MAX);
+
+    // Walls can't be covered in hazards, food or snakes
+    for t in [Tile::Hazard(1), Tile::Food, Tile::Snake, Tile::Head] {
+        b.add(wall, t);
+        assert_eq!(b.get(wall), Tile::Wall);
+    }
+    b.clear_snake(wall);
+    b.remove_hazards(wall, 1);
+    assert_eq!(b.get(wall), Tile::Wall);
+}
@@ -1,5 +1,5 @@
 use crate::{
-    logic::{floodfill, scoring::winter, Game},
+    logic::{floodfill, scoring::winter, Game, Point, Tile},
     protocol,
 };
 
@@ -7,7 +7,7 @@ use crate::{
 fn voronoi_fill() {
     let request: protocol::Request =
         serde_json::from_str(include_str!("data/wrapped_rivers_and_lakes_opening.json")).unwrap();
-    let game = Game::from(&request);
+    let game = Game::try_from(&request).unwrap();
 
     // Opening should have 20 tiles for each snake.
 
@@ -19,11 +19,31 @@ fn voronoi_fill() {
     assert_eq!(floodfill::me(&game), 20);
 }
 
+#[test]
+fn walls_block_floodfill() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/wrapped_rivers_and_lakes_opening.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+
+    // The hazards are deadly in this game, walls should have the same effect
+    for p in request.board.hazards.iter() {
+        game.board.set(p, Tile::Wall);
+    }
+    game.rules.hazard_damage_per_turn = 0;
+    assert_eq!(game.board.get(&request.board.hazards[0]), Tile::Wall);
+    assert_eq!(game.board.hazard_count(&Point { x: 0, y: 0 }), 0);
+
+    assert_eq!(floodfill::me(&game), 20);
+    let all_scores = winter::floodfill::<{ winter::NumType::MAX }>(&game);
+    assert_eq!(all_scores[0].tile_count, 20);
+    assert_eq!(all_scores[0].hazard_count, 0);
+}
+
 #[test]
 fn winter_fill() {
     let request: protocol::Request =
         serde_json::from_str(include_str!("data/wrapped_rivers_and_lakes_opening.json")).unwrap();
-    let game = Game::from(&request);
+    let game = Game::try_from(&request).unwrap();
 
     let all_scores = winter::floodfill::<{ winter::NumType::MAX }>(&game);
     for (i, score) in all_scores[0..4].iter().enumerate() {
@@ -42,7 +62,7 @@ fn winter_fill() {
 fn winter_fill_survivable_hazards() {
     let request: protocol::Request =
         serde_json::from_str(include_str!("data/wrapped_rivers_and_lakes_opening.json")).unwrap();
-    let mut game = Game::from(&request);
+    let mut game = Game::try_from(&request).unwrap();
     game.rules.hazard_damage_per_turn = 25;
 
     let all_scores = winter::floodfill::<{ winter::NumType::MAX }>(&game);
@@ -57,3 +77,74 @@ fn winter_fill_survivable_hazards() {
         assert_eq!(score.food_count, 1);
     }
 }
+
+#[test]
+fn winter_fill_squads() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/squads.json")).unwrap();
+    let game = Game::try_from(&request).unwrap();
+
+    let scores = winter::floodfill::<{ winter::NumType::MAX }>(&game);
+    // Teammates never run into each other
+    assert_eq!(scores.distance_to_collision(0, 1), winter::NumType::MAX);
+    assert_eq!(scores.distance_to_collision(1, 0), winter::NumType::MAX);
+    assert!(scores.distance_to_collision(0, 2) < winter::NumType::MAX);
+}
+
+fn large_request(size: usize, snakes: usize) -> protocol::Request {
+    let mut request: protocol::Request =
+        serde_json::from_str(include_str!("data/you_eat_food.json")).unwrap();
+    request.board.width = size;
+    request.board.height = size;
+    request.board.food.clear();
+    request.board.hazards.clear();
+
+    let template = request.you.clone();
+    request.board.snakes = (0..snakes)
+        .map(|i| {
+            let mut snake = template.clone();
+            snake.id = format!("snake-{}", i);
+            let (x, y) = (3 * (i % 16) as i8, 4 * (i / 16) as i8);
+            snake.body = (0..3).map(|dy| Point { x, y: y + 2 - dy }).collect();
+            snake.head = snake.body[0];
+            snake
+        })
+        .collect();
+    request.you = request.board.snakes[0].clone();
+    request
+}
+
+#[test]
+fn large_boards_and_many_snakes() {
+    let request = large_request(60, 20);
+    let game = Game::try_from(&request).unwrap();
+    assert_eq!(game.others.len(), 19);
+
+    let voronoi = floodfill::all(&game);
+    let claimed: usize = voronoi.values().sum();
+    assert!(claimed > 60 * 60 / 2 && claimed <= 60 * 60);
+
+    let scores = winter::floodfill::<{ winter::NumType::MAX }>(&game);
+    assert_eq!(scores.len(), 20);
+    let claimed: usize = scores.iter().map(|s| s.tile_count as usize).sum();
+    assert!(claimed > 60 * 60 / 2 && claimed <= 60 * 60);
+    assert!(scores.distance_to_collision(0, 16) < winter::NumType::MAX);
+
+    // A single snake on a large open board claims far more tiles than fit in
+    // a u8, but it starves before it can reach all of them in the winter fill
+    let game = Game::try_from(&large_request(127, 1)).unwrap();
+    assert_eq!(floodfill::me(&game), 127 * 127 - 2);
+    let scores = winter::floodfill::<{ winter::NumType::MAX }>(&game);
+    assert!(scores[0].tile_count as usize > 127 * 127 / 2);
+}
+
+#[test]
+fn unsupported_games_are_rejected() {
+    assert!(Game::validate(&large_request(127, 20)).is_ok());
+    assert!(Game::validate(&large_request(128, 20)).is_err());
+
+    let mut request = large_request(11, 1);
+    request.board.snakes = vec![request.you.clone(); 300];
+    assert!(Game::validate(&request).is_err());
+    assert!(Game::try_from(&request).is_err());
+}
@@ -0,0 +1,109 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    logic::{Game, Point, Tile},
+    protocol,
+};
+
+fn food_count(game: &Game) -> usize {
+    let mut count = 0;
+    for x in 0..game.board.width() {
+        for y in 0..game.board.height() {
+            let p = Point {
+                x: x as i8,
+                y: y as i8,
+            };
+            if game.board.get(&p).has_food() {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[test]
+fn no_food_without_seed() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/you_eat_food.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+    game.rules.minimum_food = 10;
+
+    game.execute_moves(protocol::Direction::Up, &vec![]);
+    assert_eq!(food_count(&game), 3);
+}
+
+#[test]
+fn minimum_food() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/you_eat_food.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+    game.rules.minimum_food = 10;
+    game.rules.food_spawn_chance = 0;
+    let before = game.clone();
+
+    game.spawn_food(&mut StdRng::seed_from_u64(42));
+    assert_eq!(food_count(&game), 10);
+
+    // The minimum is reached, no more food should be added
+    game.spawn_food(&mut StdRng::seed_from_u64(42));
+    assert_eq!(food_count(&game), 10);
+
+    for p in &game.you.body {
+        assert!(!game.board.get(p).has_food());
+    }
+    for (_, p) in game.you.head.neighbours() {
+        assert_eq!(game.board.get(&p), before.board.get(&p));
+    }
+}
+
+#[test]
+fn food_spawn_chance() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/you_eat_food.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+    game.rules.minimum_food = 0;
+    game.rules.food_spawn_chance = 100;
+
+    let mut rng = StdRng::seed_from_u64(42);
+    for i in 1..=5 {
+        game.spawn_food(&mut rng);
+        assert_eq!(food_count(&game), 3 + i);
+    }
+
+    game.rules.food_spawn_chance = 0;
+    game.spawn_food(&mut rng);
+    assert_eq!(food_count(&game), 8);
+}
+
+#[test]
+fn seeded_food_is_deterministic() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/snail_mode_before.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+    game.rules.minimum_food = 8;
+    game.rules.seed = Some(1234);
+
+    let mut game1 = game.clone();
+    let mut game2 = game.clone();
+    for _ in 0..2 {
+        game1.execute_moves(protocol::Direction::Left, &vec![protocol::Direction::Down]);
+        game2.execute_moves(protocol::Direction::Left, &vec![protocol::Direction::Down]);
+    }
+    assert!(game1.board == game2.board);
+    assert!(food_count(&game1) >= 8);
+
+    game.rules.seed = Some(4321);
+    for _ in 0..2 {
+        game.execute_moves(protocol::Direction::Left, &vec![protocol::Direction::Down]);
+    }
+    assert!(game.board != game1.board);
+    for x in 0..game.board.width() {
+        for y in 0..game.board.height() {
+            let p = Point {
+                x: x as i8,
+                y: y as i8,
+            };
+            assert_ne!(game.board.get(&p), Tile::HazardWithFood(1));
+        }
+    }
+}
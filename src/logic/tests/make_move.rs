@@ -0,0 +1,106 @@
+use crate::{
+    logic::{notation::parse_game, scoring, Direction, Game},
+    protocol::{self, ALL_DIRECTIONS},
+};
+
+fn assert_same_game(a: &Game, b: &Game) {
+    assert!(a.board == b.board, "boards differ:\n{}\n{}", a, b);
+    assert_eq!(a.turn, b.turn);
+    assert_eq!(a.dead_snakes, b.dead_snakes);
+    assert_eq!(a.others.len(), b.others.len());
+    for (s1, s2) in std::iter::once(&a.you)
+        .chain(a.others.iter())
+        .zip(std::iter::once(&b.you).chain(b.others.iter()))
+    {
+        assert_eq!(s1.id, s2.id);
+        assert!(s1.body == s2.body, "bodies differ:\n{}\n{}", a, b);
+        assert!(s1.head == s2.head);
+        assert_eq!(s1.health, s2.health);
+        assert_eq!(s1.length, s2.length);
+    }
+}
+
+fn all_moves(snake_count: usize) -> Vec<Vec<Direction>> {
+    let mut res = vec![vec![]];
+    for _ in 0..snake_count {
+        res = res
+            .into_iter()
+            .flat_map(|combo| {
+                ALL_DIRECTIONS.into_iter().map(move |d| {
+                    let mut c = combo.clone();
+                    c.push(d);
+                    c
+                })
+            })
+            .collect();
+    }
+    res
+}
+
+fn check_make_unmake(game: &Game) {
+    let mut state = game.clone();
+    for moves in all_moves(game.others.len() + 1) {
+        let mut expected = game.clone();
+        expected.execute_moves(moves[0], &moves[1..]);
+
+        let undo = state.make_move(moves[0], &moves[1..]);
+        assert_same_game(&state, &expected);
+
+        // A second move on top of the first must unwind as well
+        if !state.you.dead() {
+            let next_moves = vec![Direction::Up; state.others.len()];
+            let undo2 = state.make_move(Direction::Up, &next_moves);
+            state.unmake_move(undo2);
+            assert_same_game(&state, &expected);
+        }
+
+        state.unmake_move(undo);
+        assert_same_game(&state, game);
+    }
+}
+
+#[test]
+fn make_unmake_move() {
+    for data in [
+        include_str!("data/you_eat_food.json"),
+        include_str!("data/head_to_head_equal_length.json"),
+        include_str!("data/self_collision_wrapped.json"),
+        include_str!("data/snail_mode_before.json"),
+        include_str!("data/squads.json"),
+    ] {
+        let request: protocol::Request = serde_json::from_str(data).unwrap();
+        let mut game = Game::try_from(&request).unwrap();
+        check_make_unmake(&game);
+
+        // Spawned food and royale hazards are recorded too
+        game.rules.seed = Some(1);
+        game.rules.minimum_food = 5;
+        game.rules.shrink_every_n_turns = 1;
+        check_make_unmake(&game);
+    }
+}
+
+#[test]
+fn dead_snakes_add_up() {
+    let mut game = parse_game(
+        "
+A: health=2
+B: health=1
+. . . . . . .
+. A . B . C .
+. ^ . ^ . ^ .
+",
+    )
+    .unwrap();
+    let moves = vec![Direction::Up; 2];
+    game.execute_moves(Direction::Up, &moves);
+    assert_eq!(game.dead_snakes, 1);
+
+    // Starving counts once, and isn't a kill
+    let undo = game.make_move(Direction::Left, &vec![Direction::Left]);
+    assert!(game.you.dead());
+    assert_eq!(game.dead_snakes, 2);
+    assert_eq!(scoring::kills(&game), 1);
+    game.unmake_move(undo);
+    assert_eq!(game.dead_snakes, 1);
+}
@@ -0,0 +1,187 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    logic::{
+        maps::{self, Map, MapKind},
+        Board, Game, Point, Tile,
+    },
+    protocol,
+};
+
+fn hazards(board: &Board) -> Vec<Point> {
+    let mut res = vec![];
+    for x in 0..board.width() as i8 {
+        for y in 0..board.height() as i8 {
+            if board.hazard_count(&Point { x, y }) > 0 {
+                res.push(Point { x, y });
+            }
+        }
+    }
+    res
+}
+
+#[test]
+fn map_from_request() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/snail_mode_before.json")).unwrap();
+    assert_eq!(Game::try_from(&request).unwrap().map, MapKind::SnailMode);
+
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/wrapped_rivers_and_lakes_opening.json")).unwrap();
+    assert_eq!(Game::try_from(&request).unwrap().map, MapKind::IslandsBridges);
+
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/self_collision_wrapped.json")).unwrap();
+    assert_eq!(Game::try_from(&request).unwrap().map, MapKind::Royale);
+
+    for map in [
+        MapKind::Standard,
+        MapKind::Royale,
+        MapKind::SnailMode,
+        MapKind::RiversAndLakes,
+        MapKind::IslandsBridges,
+        MapKind::HazardPits,
+        MapKind::ArcadeMaze,
+    ] {
+        assert_eq!(MapKind::from_name(map.get().name()), Some(map));
+    }
+}
+
+#[test]
+fn islands_bridges_layout() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/wrapped_rivers_and_lakes_opening.json")).unwrap();
+    let game = Game::try_from(&request).unwrap();
+
+    let mut empty = game.clone();
+    empty.board = Board::new(11, 11);
+    maps::IslandsBridges.setup_board(&mut empty, &mut StdRng::seed_from_u64(1));
+
+    assert_eq!(hazards(&empty.board), hazards(&game.board));
+
+    // Static hazards don't change during the game
+    let mut next = game.clone();
+    next.execute_moves(protocol::Direction::Up, &vec![protocol::Direction::Down]);
+    assert_eq!(hazards(&next.board), hazards(&game.board));
+}
+
+#[test]
+fn standard_setup() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/you_eat_food.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+    game.board = Board::new(11, 11);
+    game.others.truncate(3);
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let spawns = maps::Standard.spawn_points(11, 11, 4, &mut rng);
+    assert_eq!(spawns.len(), 4);
+    for (i, p) in spawns.iter().enumerate() {
+        assert!(!spawns[i + 1..].contains(p));
+        assert!(!p.out_of_bounds(11, 11));
+    }
+    game.you.head = spawns[0];
+    for (i, s) in game.others.iter_mut().enumerate() {
+        s.head = spawns[i + 1];
+    }
+
+    maps::Standard.setup_board(&mut game, &mut rng);
+    let mut food = 0;
+    for x in 0..11 {
+        for y in 0..11 {
+            if game.board.get(&Point { x, y }).has_food() {
+                food += 1;
+            }
+        }
+    }
+    assert_eq!(food, 1 + game.others.len() + 1);
+    assert_eq!(game.board.get(&Point { x: 5, y: 5 }), Tile::Food);
+}
+
+#[test]
+fn hazard_pits_cycle() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/you_eat_food.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+    game.board = Board::new(11, 11);
+    game.others.clear();
+    game.you.health = 100;
+    game.map = MapKind::HazardPits;
+    game.rules.hazard_damage_per_turn = 0;
+
+    game.rules.shrink_every_n_turns = 10;
+
+    assert_eq!(maps::pit_depth(0, 10), 0);
+    let pit = Point { x: 5, y: 5 };
+    let mut max_depth = 0;
+    for _ in 0..70 {
+        game.you.health = 100;
+        game.execute_moves(protocol::Direction::Up, &vec![]);
+        let depth = maps::pit_depth(game.turn, 10);
+        assert_eq!(game.board.hazard_count(&pit), depth);
+        max_depth = max_depth.max(depth);
+    }
+    assert_eq!(max_depth, 4);
+    assert_eq!(maps::pit_depth(70, 10), 2);
+
+    // Hazards from the request are kept until the pits change
+    game.board.add(&pit, Tile::Hazard(1));
+    game.execute_moves(protocol::Direction::Up, &vec![]);
+    assert_eq!(game.board.hazard_count(&pit), 3);
+    assert_eq!(maps::pit_depth(0, 0), 0);
+    assert!(maps::HazardPits.fits(11, 11));
+    assert!(!maps::HazardPits.fits(19, 19));
+}
+
+#[test]
+fn arcade_maze_setup() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/you_eat_food.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+    game.board = Board::new(19, 21);
+    game.others.truncate(1);
+    game.map = MapKind::ArcadeMaze;
+    game.rules.seed = Some(99);
+    game.rules.minimum_food = 20;
+
+    let mut rng = StdRng::seed_from_u64(3);
+    let spawns = maps::ArcadeMaze.spawn_points(19, 21, 2, &mut rng);
+    maps::ArcadeMaze.setup_board(&mut game, &mut rng);
+    for p in spawns.iter() {
+        assert_eq!(game.board.get(p), Tile::Empty);
+    }
+    assert_eq!(game.board.get(&Point { x: 0, y: 0 }), Tile::Wall);
+    assert_eq!(game.board.get(&Point { x: 0, y: 11 }), Tile::Empty);
+
+    // Food only spawns on a few fixed tiles
+    maps::ArcadeMaze.post_update(&mut game);
+    let mut food = 0;
+    for x in 0..19 {
+        for y in 0..21 {
+            let t = game.board.get(&Point { x, y });
+            assert_ne!(t, Tile::HazardWithFood(1));
+            if t.has_food() {
+                food += 1;
+            }
+        }
+    }
+    assert!(food > 0 && food <= 6);
+}
+
+#[test]
+fn walls_are_deadly() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/you_eat_food.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+    let wall = game.you.head.neighbour(protocol::Direction::Up);
+    game.board.set(&wall, Tile::Wall);
+
+    let mut next = game.clone();
+    next.execute_moves(protocol::Direction::Up, &vec![]);
+    assert!(next.you.dead());
+    assert_eq!(next.board.get(&wall), Tile::Wall);
+
+    let mut next = game.clone();
+    next.execute_moves(protocol::Direction::Down, &vec![]);
+    assert!(!next.you.dead());
+}
@@ -4,15 +4,24 @@ use crate::protocol::{self};
 
 use super::{Direction, Game};
 
+mod bitboard;
 mod board;
 mod floodfill;
+mod food_spawning;
+mod make_move;
+mod maps;
+mod notation;
+mod requests;
+mod royale;
 mod snail_mode;
+mod squads;
+mod zobrist;
 
 #[test]
 fn head_to_head_collision_equal_length() {
     let request: protocol::Request =
         serde_json::from_str(include_str!("data/head_to_head_equal_length.json")).unwrap();
-    let mut game = Game::from(&request);
+    let mut game = Game::try_from(&request).unwrap();
 
     game.execute_moves(Direction::Down, &vec![Direction::Up, Direction::Up]);
     assert_eq!(game.you.health, 0);
@@ -24,7 +33,7 @@ fn head_to_head_collision_equal_length() {
 fn food_pickup() {
     let request: protocol::Request =
         serde_json::from_str(include_str!("data/you_eat_food.json")).unwrap();
-    let mut game = Game::from(&request);
+    let mut game = Game::try_from(&request).unwrap();
 
     game.execute_moves(Direction::Right, &vec![]);
     assert_eq!(game.you.health, 100);
@@ -57,7 +66,7 @@ fn food_pickup() {
 fn self_collision_when_wrapping() {
     let request: protocol::Request =
         serde_json::from_str(include_str!("data/self_collision_wrapped.json")).unwrap();
-    let mut game = Game::from(&request);
+    let mut game = Game::try_from(&request).unwrap();
 
     game.execute_moves(Direction::Up, &vec![]);
     assert_eq!(game.you.health, 0);
@@ -0,0 +1,125 @@
+use crate::logic::{
+    game::GameMode,
+    notation::{parse_game, parse_request, write},
+    Direction, Point, Tile,
+};
+
+const POSITION: &str = "
+ruleset: wrapped
+turn: 12
+hazard_damage: 14
+B: health=45 length=6 squad=red
+A: health=90
+. . + . .
+. > A . .
+. ^ . .!2 .
+< B < < <!
+. . . . .
+";
+
+#[test]
+fn parse_position() {
+    let game = parse_game(POSITION).unwrap();
+    assert!(game.rules.game_mode == GameMode::Wrapped);
+    assert_eq!(game.turn, 12);
+    assert_eq!(game.rules.hazard_damage_per_turn, 14);
+
+    assert_eq!(game.you.health, 90);
+    assert_eq!(game.you.length, 3);
+    assert_eq!(
+        game.you.body,
+        vec![
+            Point { x: 2, y: 3 },
+            Point { x: 1, y: 3 },
+            Point { x: 1, y: 2 }
+        ]
+    );
+
+    // B's tail wraps around to the other side of the board and is stacked
+    let other = &game.others[0];
+    assert_eq!(other.health, 45);
+    assert_eq!(other.length, 6);
+    assert_eq!(other.head, Point { x: 1, y: 1 });
+    assert_eq!(
+        other.body.iter().skip(2).collect::<Vec<_>>(),
+        vec![
+            &Point { x: 3, y: 1 },
+            &Point { x: 4, y: 1 },
+            &Point { x: 0, y: 1 },
+            &Point { x: 0, y: 1 }
+        ]
+    );
+    assert_eq!(game.board.get(&Point { x: 0, y: 1 }), Tile::Snake);
+
+    assert!(game.board.get(&Point { x: 2, y: 4 }).has_food());
+    assert_eq!(game.board.hazard_count(&Point { x: 3, y: 2 }), 2);
+    assert_eq!(game.board.hazard_count(&Point { x: 4, y: 1 }), 1);
+}
+
+#[test]
+fn write_parsed_position() {
+    let game = parse_game(POSITION).unwrap();
+    let text = write(&game);
+    let parsed = parse_game(&text).unwrap();
+    assert!(parsed == game, "{}", text);
+    assert_eq!(write(&parsed), text);
+
+    let req = parse_request(POSITION).unwrap();
+    assert_eq!(req.you.id, "A");
+    assert_eq!(req.board.snakes[1].squad, "red");
+    assert_eq!(req.board.hazards.len(), 3);
+}
+
+#[test]
+fn walls_and_you() {
+    let text = "
+you: B
+seed: 7
+A . # .
+^ . # B!
+";
+    let game = parse_game(text).unwrap();
+    assert_eq!(game.board.get(&Point { x: 2, y: 1 }), Tile::Wall);
+    assert_eq!(game.rules.seed, Some(7));
+    assert_eq!(game.you.head, Point { x: 3, y: 0 });
+    assert_eq!(game.others[0].head, Point { x: 0, y: 1 });
+    assert!(parse_game(&write(&game)).unwrap() == game);
+
+    assert!(parse_request(text).is_err());
+}
+
+#[test]
+fn head_to_head() {
+    let mut game = parse_game(
+        "
+. . . . .
+> A . B <
+",
+    )
+    .unwrap();
+    game.execute_moves(Direction::Right, &vec![Direction::Left]);
+    assert!(game.you.dead());
+    assert!(game.others.iter().all(|s| s.dead()));
+}
+
+#[test]
+fn invalid_positions() {
+    for text in [
+        // Rows of different lengths
+        "A . .\n. .",
+        // Unknown tile
+        "A x .",
+        // Segment that isn't connected to a head
+        "A . <",
+        // Snake settings for a snake that isn't on the board
+        "B: health=10\nA . .",
+        // Body longer than the length
+        "A: length=1\nA < .",
+        "speed: 10\nA . .",
+        "A . .\nturn: 10",
+        "A . A",
+        "",
+    ] {
+        assert!(parse_game(text).is_err(), "{}", text);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{
+    logic::{game::GameMode, Direction, Game},
+    protocol,
+};
+
+// Request shapes sent by different engine versions, oldest first
+const REQUESTS: [(&str, &str); 5] = [
+    ("cli v1.0", include_str!("data/protocol/cli_v1.0.json")),
+    (
+        "cli v1.1 squad",
+        include_str!("data/protocol/cli_v1.1_squad.json"),
+    ),
+    (
+        "engine v1.2 hazard map",
+        include_str!("data/protocol/engine_v1.2_hazard_map.json"),
+    ),
+    (
+        "future unknown fields",
+        include_str!("data/protocol/future_unknown_fields.json"),
+    ),
+    (
+        "custom engine minimal",
+        include_str!("data/protocol/custom_engine_minimal.json"),
+    ),
+];
+
+fn parse(name: &str, data: &str) -> protocol::Request {
+    match serde_json::from_str(data) {
+        Ok(r) => r,
+        Err(e) => panic!("failed to parse {} request: {}", name, e),
+    }
+}
+
+#[test]
+fn all_request_versions_parse() {
+    for (name, data) in REQUESTS {
+        let request = parse(name, data);
+        let game = Game::try_from(&request).unwrap();
+        assert_eq!(game.you.length, request.you.length, "{}", name);
+        assert_eq!(
+            game.others.len() + 1,
+            request.board.snakes.len(),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn missing_fields_get_defaults() {
+    let request = parse(REQUESTS[0].0, REQUESTS[0].1);
+    let settings = &request.game.ruleset.settings;
+    assert_eq!(settings.food_spawn_chance, 15);
+    assert_eq!(settings.minimum_food, 1);
+    assert_eq!(settings.hazard_damage_per_turn, 14);
+    assert_eq!(request.you.squad, "");
+    assert_eq!(request.you.customizations.color, "");
+
+    let request = parse(REQUESTS[4].0, REQUESTS[4].1);
+    assert_eq!(request.game.ruleset.name, "standard");
+    assert_eq!(request.game.timeout, 500);
+    assert!(request.board.food.is_empty() && request.board.hazards.is_empty());
+    let game = Game::try_from(&request).unwrap();
+    assert!(game.rules.game_mode == GameMode::Standard);
+    assert_eq!((game.board.width(), game.board.height()), (13, 9));
+
+    let request = parse(REQUESTS[1].0, REQUESTS[1].1);
+    assert!(request.game.ruleset.settings.squad.shared_health);
+    assert_eq!(Game::try_from(&request).unwrap().you.squad, 1);
+}
+
+#[test]
+fn new_and_unknown_fields_round_trip() {
+    let request = parse(REQUESTS[2].0, REQUESTS[2].1);
+    assert_eq!(request.game.ruleset.settings.hazard_map, "hz_spiral");
+    assert_eq!(
+        request.game.ruleset.settings.hazard_map_author,
+        "altersaddle"
+    );
+
+    let request = parse(REQUESTS[3].0, REQUESTS[3].1);
+    assert_eq!(request.extra.0["rulesVersion"], 2);
+    assert_eq!(request.game.extra.0["seed"], 1234);
+    assert_eq!(request.game.ruleset.settings.extra.0["foodValue"], 2);
+    assert_eq!(request.you.extra.0["team"], "alpha");
+    assert_eq!(request.you.customizations.extra.0["pattern"], "stripes");
+    assert!(request.board.extra.0.contains_key("walls"));
+
+    // Serializing the request gives back the original JSON
+    let original: serde_json::Value = serde_json::from_str(REQUESTS[3].1).unwrap();
+    assert_eq!(serde_json::to_value(&request).unwrap(), original);
+}
+
+#[test]
+fn game_to_request() {
+    for (name, data) in REQUESTS {
+        let request = parse(name, data);
+        let game = Game::try_from(&request).unwrap();
+        let converted = protocol::Request::from(&game);
+
+        assert!(Game::try_from(&converted).unwrap() == game, "{}", name);
+        assert!(converted.game == request.game, "{}", name);
+        assert!(converted.you == request.you, "{}", name);
+        assert!(converted.board.snakes == request.board.snakes, "{}", name);
+        assert_eq!(
+            converted.board.food.len(),
+            request.board.food.len(),
+            "{}",
+            name
+        );
+    }
+
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/squads.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+    game.execute_moves(Direction::Up, &vec![Direction::Up; game.others.len()]);
+
+    // Any snake can be used as the perspective
+    let other = &game.others[1];
+    let converted = game.request_for(other.id).unwrap();
+    assert_eq!(converted.turn, request.turn + 1);
+    assert_eq!(converted.you.id, request.board.snakes[2].id);
+    assert!(converted.you.customizations == request.board.snakes[2].customizations);
+    assert_eq!(converted.you.body, other.body);
+    let from_other = Game::try_from(&converted).unwrap();
+    assert_eq!(from_other.you.body, other.body);
+    assert_eq!(from_other.others.len(), game.others.len());
+    assert!(game.request_for(100).is_none());
+}
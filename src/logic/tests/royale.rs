@@ -0,0 +1,117 @@
+use crate::{
+    logic::{
+        maps::MapKind,
+        royale::{self, SafeZone},
+        Direction, Game, Point,
+    },
+    protocol,
+};
+
+#[test]
+fn safe_zone_from_board() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/self_collision_wrapped.json")).unwrap();
+    let game = Game::try_from(&request).unwrap();
+
+    assert_eq!(game.rules.shrink_every_n_turns, 25);
+    assert_eq!(
+        game.royale_zone(0),
+        Some(SafeZone {
+            min_x: 1,
+            max_x: 8,
+            min_y: 1,
+            max_y: 10,
+        })
+    );
+}
+
+#[test]
+fn royale_shrinks_on_schedule() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/self_collision_wrapped.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+    // Put the snakes somewhere they'll survive for a few turns
+    game.others.clear();
+    game.you.health = 100;
+
+    let predicted = game.royale_hazards(6);
+    assert_eq!(predicted.len(), 41 + 8);
+
+    let moves = [
+        Direction::Up,
+        Direction::Right,
+        Direction::Right,
+        Direction::Up,
+        Direction::Up,
+    ];
+    for dir in moves {
+        game.execute_moves(dir, &vec![]);
+        assert_eq!(game.royale_hazards(0).len(), 41);
+    }
+
+    // Turn 125 is the fifth time hazards grow
+    game.execute_moves(Direction::Up, &vec![]);
+    assert_eq!(game.turn, 125);
+    let mut hazards = vec![];
+    for x in 0..11 {
+        for y in 0..11 {
+            let p = Point { x, y };
+            if game.board.hazard_count(&p) > 0 {
+                hazards.push(p);
+            }
+        }
+    }
+    assert_eq!(hazards.len(), predicted.len());
+    for p in predicted {
+        assert_eq!(game.board.hazard_count(&p), 1, "expected hazard at {}", p);
+    }
+    for x in 1..9 {
+        assert_eq!(game.board.hazard_count(&Point { x, y: 10 }), 1);
+    }
+}
+
+#[test]
+fn seeded_safe_zone() {
+    let zone = royale::safe_zone(11, 11, 24, 25, 42);
+    assert_eq!(zone, SafeZone::new(11, 11));
+
+    let mut previous = zone;
+    for shrinks in 1..30 {
+        let zone = royale::safe_zone(11, 11, shrinks * 25, 25, 42);
+        assert_eq!(zone, royale::safe_zone(11, 11, shrinks * 25 + 24, 25, 42));
+        let size =
+            |z: &SafeZone| (z.max_x - z.min_x + 1) as usize * (z.max_y - z.min_y + 1) as usize;
+        assert!(size(&zone) <= size(&previous));
+        assert!(zone.min_x <= zone.max_x && zone.min_y <= zone.max_y);
+        previous = zone;
+    }
+}
+
+#[test]
+fn seeded_game_matches_safe_zone() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/you_eat_food.json")).unwrap();
+    let mut game = Game::try_from(&request).unwrap();
+    game.map = MapKind::Royale;
+    game.rules.shrink_every_n_turns = 2;
+    game.rules.seed = Some(7);
+    game.rules.minimum_food = 0;
+    game.rules.food_spawn_chance = 0;
+    game.board.remove_hazards(&request.board.hazards[0], 1);
+
+    for dir in [
+        Direction::Right,
+        Direction::Right,
+        Direction::Up,
+        Direction::Up,
+    ] {
+        game.execute_moves(dir, &vec![]);
+        let zone = royale::safe_zone(11, 11, game.turn, 2, 7);
+        for x in 0..11 {
+            for y in 0..11 {
+                let p = Point { x, y };
+                assert_eq!(game.board.hazard_count(&p) > 0, !zone.contains(&p));
+            }
+        }
+    }
+}
@@ -5,7 +5,7 @@ fn snail_mode_apply_moves() {
     let request: protocol::Request =
         serde_json::from_str(include_str!("data/snail_mode_before.json")).unwrap();
 
-    let before_game = Game::from(&request);
+    let before_game = Game::try_from(&request).unwrap();
     let mut predicted_game = before_game.clone();
 
     predicted_game.execute_moves(protocol::Direction::Left, &vec![protocol::Direction::Down]);
@@ -13,7 +13,7 @@ fn snail_mode_apply_moves() {
     let request: protocol::Request =
         serde_json::from_str(include_str!("data/snail_mode_after.json")).unwrap();
 
-    let after_game = Game::from(&request);
+    let after_game = Game::try_from(&request).unwrap();
 
     println!("before: {}", before_game);
     println!("predicted: {}", predicted_game);
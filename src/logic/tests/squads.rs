@@ -0,0 +1,92 @@
+use crate::{
+    logic::{game::GameMode, Direction, Game, Point, Tile},
+    protocol,
+};
+
+fn squad_game() -> Game {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/squads.json")).unwrap();
+    Game::try_from(&request).unwrap()
+}
+
+// You move into your teammate's tail, the first enemy moves into your tail
+// and the second enemy moves into open space. Your teammate eats.
+const YOUR_MOVE: Direction = Direction::Right;
+const OTHER_MOVES: [Direction; 3] = [Direction::Up, Direction::Right, Direction::Up];
+
+#[test]
+fn squads_from_request() {
+    let game = squad_game();
+    assert!(game.rules.game_mode == GameMode::Squad);
+    assert_eq!(game.you.squad, 1);
+    assert_eq!(game.others[0].squad, 1);
+    assert_eq!(game.others[1].squad, 2);
+    assert_eq!(game.others[2].squad, 2);
+    assert!(game.you.same_squad(&game.others[0]));
+    assert!(!game.you.same_squad(&game.you));
+    assert!(!game.you.same_squad(&game.others[1]));
+}
+
+#[test]
+fn squad_rules() {
+    let mut game = squad_game();
+    game.execute_moves(YOUR_MOVE, &OTHER_MOVES.to_vec());
+
+    assert!(!game.you.dead());
+    assert_eq!(game.others.len(), 1);
+    assert_eq!(game.others[0].squad, game.you.squad);
+    assert_eq!(game.dead_snakes, 2);
+
+    // Shared health and length, after your teammate picked up food
+    assert_eq!(game.you.health, 100);
+    assert_eq!(game.you.length, 4);
+    assert_eq!(game.you.body.len(), 4);
+    assert_eq!(game.others[0].length, 4);
+
+    // Overlapping bodies are both kept on the board
+    assert_eq!(game.board.get(&Point { x: 3, y: 2 }), Tile::Head);
+    assert_eq!(game.board.get(&Point { x: 2, y: 1 }), Tile::Snake);
+    assert_eq!(game.board.get(&Point { x: 4, y: 2 }), Tile::Snake);
+
+    game.execute_moves(Direction::Up, &vec![Direction::Up]);
+    assert!(!game.you.dead());
+    assert!(game.board.get(&Point { x: 3, y: 2 }).is_snake());
+}
+
+#[test]
+fn squad_without_body_collisions() {
+    let mut game = squad_game();
+    game.rules.squad.allow_body_collisions = false;
+    game.execute_moves(YOUR_MOVE, &OTHER_MOVES.to_vec());
+
+    // Shared elimination takes out both squads
+    assert!(game.you.dead());
+    assert_eq!(game.others.len(), 0);
+    assert_eq!(game.dead_snakes, 4);
+}
+
+#[test]
+fn squad_without_shared_elimination() {
+    let mut game = squad_game();
+    game.rules.squad.shared_elimination = false;
+    game.rules.squad.shared_health = false;
+    game.execute_moves(YOUR_MOVE, &OTHER_MOVES.to_vec());
+
+    assert!(!game.you.dead());
+    assert_eq!(game.you.health, 49);
+    assert_eq!(game.you.length, 4);
+    assert_eq!(game.others.len(), 2);
+    assert_eq!(game.others[1].squad, 2);
+}
+
+#[test]
+fn squad_rules_only_apply_to_squad_games() {
+    let mut request: protocol::Request =
+        serde_json::from_str(include_str!("data/squads.json")).unwrap();
+    request.game.ruleset.name = "standard".to_string();
+    let mut game = Game::try_from(&request).unwrap();
+    game.execute_moves(YOUR_MOVE, &OTHER_MOVES.to_vec());
+
+    assert!(game.you.dead());
+    assert_eq!(game.others.len(), 2);
+}
@@ -0,0 +1,97 @@
+use crate::{
+    logic::{zobrist, Board, Direction, Game, Point},
+    protocol::{self, ALL_DIRECTIONS},
+};
+
+fn rebuilt_board_hash(board: &Board) -> u64 {
+    let mut rebuilt = Board::new(board.width() as usize, board.height() as usize);
+    for x in 0..board.width() as i8 {
+        for y in 0..board.height() as i8 {
+            let p = Point { x, y };
+            rebuilt.set(&p, board.get(&p));
+        }
+    }
+    rebuilt.zobrist()
+}
+
+fn check_hashes(game: &Game) {
+    assert_eq!(game.board.zobrist(), rebuilt_board_hash(&game.board));
+    for snake in std::iter::once(&game.you).chain(game.others.iter()) {
+        assert_eq!(snake.hash, zobrist::body_hash(snake.body.iter()));
+    }
+}
+
+#[test]
+fn incremental_hashes() {
+    for data in [
+        include_str!("data/you_eat_food.json"),
+        include_str!("data/head_to_head_equal_length.json"),
+        include_str!("data/self_collision_wrapped.json"),
+        include_str!("data/snail_mode_before.json"),
+        include_str!("data/squads.json"),
+    ] {
+        let request: protocol::Request = serde_json::from_str(data).unwrap();
+        let game = Game::try_from(&request).unwrap();
+        check_hashes(&game);
+
+        for dir in ALL_DIRECTIONS {
+            let mut next = game.clone();
+            let others = vec![dir; game.others.len()];
+            next.make_move(dir, &others);
+            check_hashes(&next);
+            for _ in 0..5 {
+                next.execute_moves(Direction::Up, &vec![Direction::Up; next.others.len()]);
+                check_hashes(&next);
+            }
+
+            let mut unmade = game.clone();
+            let undo = unmade.make_move(dir, &others);
+            unmade.unmake_move(undo);
+            assert_eq!(unmade.zobrist(), game.zobrist());
+        }
+    }
+}
+
+#[test]
+fn positions_hash_differently() {
+    let request: protocol::Request =
+        serde_json::from_str(include_str!("data/head_to_head_equal_length.json")).unwrap();
+    let game = Game::try_from(&request).unwrap();
+
+    let mut hashes = vec![game.zobrist()];
+    for dir in ALL_DIRECTIONS {
+        let mut next = game.clone();
+        next.execute_moves(dir, &vec![Direction::Up; game.others.len()]);
+        hashes.push(next.zobrist());
+    }
+    for (i, h) in hashes.iter().enumerate() {
+        assert!(!hashes[i + 1..].contains(h));
+    }
+
+    // Health only matters in buckets
+    let mut other = game.clone();
+    other.you.health = game.you.health / zobrist::HEALTH_BUCKET_SIZE * zobrist::HEALTH_BUCKET_SIZE;
+    assert_eq!(other.zobrist(), game.zobrist());
+    other.you.health -= 1;
+    assert_ne!(other.zobrist(), game.zobrist());
+
+    // Unless the exact hash is used, which has the turn as well
+    let mut other = game.clone();
+    other.you.health += 1;
+    assert_ne!(other.exact_zobrist(), game.exact_zobrist());
+    let mut other = game.clone();
+    other.turn += 2;
+    assert_eq!(other.zobrist(), game.zobrist());
+    assert_ne!(other.exact_zobrist(), game.exact_zobrist());
+    // And the kills the scorers count
+    let mut other = game.clone();
+    other.dead_snakes += 1;
+    assert_eq!(other.zobrist(), game.zobrist());
+    assert_ne!(other.exact_zobrist(), game.exact_zobrist());
+
+    // The same tiles in a different order are a different body
+    let mut other = game.clone();
+    other.you.body.swap(1, 2);
+    other.you.hash = zobrist::body_hash(other.you.body.iter());
+    assert_ne!(other.zobrist(), game.zobrist());
+}
@@ -0,0 +1,104 @@
+use super::{Point, Snake};
+
+// Zobrist keys are derived from the item they describe with a splitmix64
+// hash, instead of being looked up in tables of random numbers. This keeps
+// them identical across runs and independent of the board size.
+
+const TILE: u64 = 1 << 60;
+const HEAD: u64 = 2 << 60;
+const SEGMENT: u64 = 3 << 60;
+const SNAKE: u64 = 4 << 60;
+const TURN: u64 = 5 << 60;
+const HEALTH: u64 = 6 << 60;
+const EXACT_TURN: u64 = 7 << 60;
+const DEAD_SNAKES: u64 = 8 << 60;
+
+/// Snakes with a health in the same bucket hash to the same value.
+pub const HEALTH_BUCKET_SIZE: i8 = 10;
+
+#[inline(always)]
+pub fn mix(v: u64) -> u64 {
+    let mut z = v.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[inline(always)]
+fn point_bits(p: &Point) -> u64 {
+    (p.x as u8 as u64) << 8 | p.y as u8 as u64
+}
+
+/// tile_key hashes the packed value of a board tile. Empty tiles hash to
+/// zero, so an empty board does too.
+#[inline(always)]
+pub fn tile_key(idx: usize, value: u8) -> u64 {
+    if value == 0 {
+        0
+    } else {
+        mix(TILE | (idx as u64) << 8 | value as u64)
+    }
+}
+
+#[inline(always)]
+pub fn head_key(p: &Point) -> u64 {
+    mix(HEAD | point_bits(p))
+}
+
+/// segment_key hashes a body segment by its position and the position of
+/// the segment before it, so the order of the body is part of the hash.
+#[inline(always)]
+pub fn segment_key(p: &Point, prev: &Point) -> u64 {
+    mix(SEGMENT | point_bits(p) << 16 | point_bits(prev))
+}
+
+/// body_hash calculates Snake::hash from scratch. Segments stacked on top
+/// of the segment before them are skipped, they follow from the length.
+pub fn body_hash<'a, I: Iterator<Item = &'a Point>>(mut body: I) -> u64 {
+    let mut prev = match body.next() {
+        Some(head) => head,
+        None => return 0,
+    };
+    let mut hash = head_key(prev);
+    for p in body {
+        if p != prev {
+            hash ^= segment_key(p, prev);
+        }
+        prev = p;
+    }
+    hash
+}
+
+/// snake_key combines the body hash of a snake with its number, length
+/// and health bucket.
+#[inline(always)]
+pub fn snake_key(snake: &Snake) -> u64 {
+    let health_bucket = (snake.health / HEALTH_BUCKET_SIZE) as u8 as u64;
+    let stats = (snake.id as u64) << 32 | (snake.length as u64 & 0xffff) << 8 | health_bucket;
+    mix(snake.hash ^ mix(SNAKE | stats))
+}
+
+#[inline(always)]
+pub fn turn_key(turn: usize) -> u64 {
+    mix(TURN | (turn % 2) as u64)
+}
+
+/// health_key hashes the exact health of a snake, where snake_key only has
+/// its health bucket.
+#[inline(always)]
+pub fn health_key(snake: &Snake) -> u64 {
+    mix(HEALTH | (snake.id as u64) << 8 | snake.health as u8 as u64)
+}
+
+/// exact_turn_key hashes the turn, where turn_key only has its parity.
+#[inline(always)]
+pub fn exact_turn_key(turn: usize) -> u64 {
+    mix(EXACT_TURN | turn as u64)
+}
+
+/// dead_snakes_key hashes the number of snakes eliminated since the game was
+/// created, scorers give points for kills counted from there.
+#[inline(always)]
+pub fn dead_snakes_key(dead_snakes: usize) -> u64 {
+    mix(DEAD_SNAKES | dead_snakes as u64)
+}
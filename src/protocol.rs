@@ -1,7 +1,25 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, hash::Hash};
 
 use serde::{Deserialize, Serialize};
 
+// Fields that are missing from a request get a default value, so requests
+// from older engines are accepted. Fields this version doesn't know about are
+// kept in Extra, so they end up in the game logs.
+
+/// Extra holds the unknown fields of a protocol object.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, Eq, PartialEq)]
+#[serde(transparent)]
+pub struct Extra(pub serde_json::Map<String, serde_json::Value>);
+
+impl Hash for Extra {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        for (k, v) in self.0.iter() {
+            k.hash(state);
+            v.to_string().hash(state);
+        }
+    }
+}
+
 #[derive(Serialize, Default, Deserialize, Hash, Eq, PartialEq, Clone, Copy)]
 pub struct Point {
     pub x: i8,
@@ -20,45 +38,102 @@ pub struct SnakeInfo {
 }
 
 // See https://docs.battlesnake.com/api/objects/game
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq)]
 pub struct Game {
     pub id: String,
+    #[serde(default)]
     pub ruleset: Ruleset,
+    #[serde(default)]
     pub map: String,
+    #[serde(default = "default_timeout")]
     pub timeout: isize,
+    #[serde(default)]
     pub source: String,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+fn default_timeout() -> isize {
+    500
 }
 
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[serde(default)]
 pub struct Ruleset {
     pub name: String,
     pub version: String,
     pub settings: RulesetSettings,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self {
+            name: "standard".to_string(),
+            version: String::new(),
+            settings: RulesetSettings::default(),
+            extra: Extra::default(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct RulesetSettings {
     pub food_spawn_chance: usize,
     pub minimum_food: usize,
     pub hazard_damage_per_turn: isize,
+    pub hazard_map: String,
+    pub hazard_map_author: String,
     pub royale: RoyaleRules,
     pub squad: SquadRules,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+// The defaults of the official rules
+impl Default for RulesetSettings {
+    fn default() -> Self {
+        Self {
+            food_spawn_chance: 15,
+            minimum_food: 1,
+            hazard_damage_per_turn: 14,
+            hazard_map: String::new(),
+            hazard_map_author: String::new(),
+            royale: RoyaleRules::default(),
+            squad: SquadRules::default(),
+            extra: Extra::default(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct RoyaleRules {
     pub shrink_every_n_turns: usize,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
-#[derive(Serialize, Deserialize, Clone, Eq, PartialEq)]
-#[serde(rename_all = "camelCase")]
+impl Default for RoyaleRules {
+    fn default() -> Self {
+        Self {
+            shrink_every_n_turns: 25,
+            extra: Extra::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Eq, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
 pub struct SquadRules {
     pub allow_body_collisions: bool,
     pub shared_elimination: bool,
     pub shared_health: bool,
     pub shared_length: bool,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 // See https://docs.battlesnake.com/api/objects/board
@@ -66,9 +141,13 @@ pub struct SquadRules {
 pub struct Board {
     pub height: usize,
     pub width: usize,
+    #[serde(default)]
     pub food: Vec<Point>,
+    #[serde(default)]
     pub hazards: Vec<Point>,
     pub snakes: Vec<Snake>,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 // See https://docs.battlesnake.com/api/objects/battlesnake
@@ -78,19 +157,28 @@ pub struct Snake {
     pub name: String,
     pub health: isize,
     pub body: VecDeque<Point>,
+    #[serde(default)]
     pub latency: String,
     pub head: Point,
     pub length: usize,
+    #[serde(default)]
     pub shout: String,
+    #[serde(default)]
     pub squad: String,
+    #[serde(default)]
     pub customizations: Customizations,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
-#[derive(Serialize, Deserialize, Clone, Hash, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Default, Hash, Eq, PartialEq)]
+#[serde(default)]
 pub struct Customizations {
     pub color: String,
     pub head: String,
     pub tail: String,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 // Request body for game start, game end and move endpoints
@@ -100,6 +188,8 @@ pub struct Request {
     pub turn: usize,
     pub board: Board,
     pub you: Snake,
+    #[serde(flatten)]
+    pub extra: Extra,
 }
 
 // Response body for move endpoint
@@ -107,6 +197,7 @@ pub struct Request {
 pub struct MoveResponse {
     #[serde(rename = "move")]
     pub direction: Direction,
+    #[serde(default)]
     pub shout: String,
 }
 
@@ -3,7 +3,7 @@ pub mod spaceheater3;
 pub mod suspicious_salami;
 
 pub use simple::SimpleSnake;
-pub use spaceheater3::Spaceheater3;
+pub use spaceheater3::{export::TreeFormat, report::SearchReport, Spaceheater3};
 use std::collections::HashMap;
 pub use suspicious_salami::Salami;
 
@@ -26,6 +26,7 @@ pub fn snakes() -> HashMap<String, Box<dyn Battlesnake + Sync + Send>> {
                 color: "#FF2400".to_string(),
                 head: "workout".to_string(),
                 tail: "rocket".to_string(),
+                ..Default::default()
             }),
         )),
     );
@@ -51,6 +52,7 @@ pub fn snakes() -> HashMap<String, Box<dyn Battlesnake + Sync + Send>> {
                 color: "#ff8400".to_string(),
                 head: "workout".to_string(),
                 tail: "rocket".to_string(),
+                ..Default::default()
             }),
         )),
     );
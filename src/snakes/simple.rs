@@ -175,6 +175,7 @@ impl Battlesnake for SimpleSnake {
     }
 
     fn make_move(&self, req: &protocol::Request) -> Result<protocol::MoveResponse, String> {
+        logic::Game::validate(req)?;
         let food = Vec::from(req.board.food.as_slice());
         let snakes = req.board.snakes.clone();
         let mut board: logic::Board = (&req.board).into();
@@ -23,6 +23,11 @@ impl<'a> AlphaBeta<'a> {
         }
     }
 
+    /// is_root is true for the window of the root of the search tree.
+    pub fn is_root(&self) -> bool {
+        self.parent.is_none()
+    }
+
     #[inline(always)]
     pub fn new_alpha_score(&self, a: i64) -> i64 {
         self.alpha.fetch_max(a, Ordering::Relaxed)
@@ -0,0 +1,245 @@
+use std::{cmp::Reverse, fmt::Write, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::logic::Direction;
+
+use super::{max::MaximizingNode, min::MinimizingNode};
+
+/// TreeFormat is the file format a search tree is exported to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeFormat {
+    Dot,
+    Json,
+}
+
+impl FromStr for TreeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(TreeFormat::Dot),
+            "json" => Ok(TreeFormat::Json),
+            _ => Err(format!("unknown tree format {}, expected dot or json", s)),
+        }
+    }
+}
+
+/// ExportNode is a node of a search tree as it is exported, with the children
+/// that didn't fit in the limits left out.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExportNode {
+    // Our move if the enemies move next, the enemy moves if we move next
+    pub moves: Vec<Direction>,
+    pub maximizing: bool,
+    // None for branches that were pruned
+    pub score: Option<i64>,
+    pub will_die: bool,
+    pub children: Vec<ExportNode>,
+    // Children left out because of the depth and width limits
+    pub omitted: usize,
+}
+
+/// export converts the tree below root, up to depth turns deep and with at
+/// most width children per node. The best children for the player to move
+/// are kept, pruned branches go last.
+pub fn export(root: &MaximizingNode, depth: usize, width: usize) -> ExportNode {
+    export_max(root, vec![], depth, width)
+}
+
+/// to_dot renders an exported tree as a Graphviz graph. Our turns are
+/// ellipses and the enemy turns boxes, pruned branches are dashed and
+/// branches where we die are red.
+pub fn to_dot(tree: &ExportNode) -> String {
+    let mut out = String::from("digraph tree {\n");
+    write_dot(tree, &mut out, &mut 0);
+    out.push_str("}\n");
+    out
+}
+
+/// to_json renders an exported tree as JSON.
+pub fn to_json(tree: &ExportNode) -> String {
+    serde_json::to_string_pretty(tree).unwrap()
+}
+
+fn export_max(
+    node: &MaximizingNode,
+    moves: Vec<Direction>,
+    depth: usize,
+    width: usize,
+) -> ExportNode {
+    let mut res = ExportNode {
+        moves,
+        maximizing: true,
+        score: node.score.map(|s| s.1),
+        will_die: node.will_die,
+        children: vec![],
+        omitted: node.children.len(),
+    };
+    if depth == 0 {
+        return res;
+    }
+
+    let mut children: Vec<_> = node.children.iter().collect();
+    children.sort_by_key(|c| (c.score.is_none(), Reverse(c.score)));
+    res.children = children
+        .into_iter()
+        .take(width)
+        .map(|c| export_min(c, depth, width))
+        .collect();
+    res.omitted -= res.children.len();
+    res
+}
+
+fn export_min(node: &MinimizingNode, depth: usize, width: usize) -> ExportNode {
+    let mut res = ExportNode {
+        moves: vec![node.my_move],
+        maximizing: false,
+        score: node.score,
+        will_die: node.will_die,
+        children: vec![],
+        omitted: 0,
+    };
+
+    if node.children.is_empty() {
+        // Solved without creating children, only the scores are known.
+        // Pruned enemy moves are scored i64::MAX.
+        let mut leaves: Vec<_> = node.leaf_scores.iter().collect();
+        leaves.sort_by_key(|(score, _)| *score);
+        res.omitted = leaves.len().saturating_sub(width);
+        res.children = leaves
+            .into_iter()
+            .take(width)
+            .map(|(score, combo)| ExportNode {
+                moves: combo.clone(),
+                maximizing: true,
+                score: Some(*score).filter(|s| *s != i64::MAX),
+                will_die: false,
+                children: vec![],
+                omitted: 0,
+            })
+            .collect();
+        return res;
+    }
+
+    let mut children: Vec<_> = node.children.iter().collect();
+    children.sort_by_key(|c| (c.score.is_none(), c.score.map(|s| s.1)));
+    res.omitted = children.len().saturating_sub(width);
+    res.children = children
+        .into_iter()
+        .take(width)
+        .map(|c| export_max(c, c.enemy_moves.clone(), depth - 1, width))
+        .collect();
+    res
+}
+
+// Writes the node and its children, and returns the id of the node
+fn write_dot(node: &ExportNode, out: &mut String, next_id: &mut usize) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+
+    let moves = if node.moves.is_empty() {
+        "root".to_string()
+    } else {
+        let moves: Vec<_> = node.moves.iter().map(|d| d.to_string()).collect();
+        moves.join(",")
+    };
+    let score = node
+        .score
+        .map(|s| s.to_string())
+        .unwrap_or("pruned".to_string());
+    let shape = if node.maximizing { "ellipse" } else { "box" };
+    let _ = write!(
+        out,
+        "  n{} [label=\"{}\\n{}\", shape={}",
+        id, moves, score, shape
+    );
+    if node.score.is_none() {
+        out.push_str(", style=dashed");
+    }
+    if node.will_die {
+        out.push_str(", color=red");
+    }
+    out.push_str("];\n");
+
+    for child in &node.children {
+        let child_id = write_dot(child, out, next_id);
+        let _ = writeln!(out, "  n{} -> n{};", id, child_id);
+    }
+    if node.omitted > 0 {
+        let omitted_id = *next_id;
+        *next_id += 1;
+        let _ = writeln!(
+            out,
+            "  n{} [label=\"{} more\", shape=plaintext];\n  n{} -> n{} [style=dotted];",
+            omitted_id, node.omitted, id, omitted_id
+        );
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{
+        logic::{notation::parse_game, scoring},
+        snakes::spaceheater3::{
+            alphabeta::AlphaBeta, ordering::MoveOrdering, transposition::TranspositionTable,
+            SearchContext, SearchMode,
+        },
+    };
+
+    fn count(node: &ExportNode) -> usize {
+        1 + node.children.iter().map(count).sum::<usize>()
+    }
+
+    #[test]
+    fn export_tree() {
+        let game = parse_game(
+            "
+. . . . . .
+. A . . B .
+. ^ . . ^ .
+. . . . . .
+",
+        )
+        .unwrap();
+        let tt = TranspositionTable::new(1 << 12);
+        let ctx = SearchContext {
+            deadline: Instant::now() + Duration::from_secs(600),
+            mode: SearchMode::Paranoid,
+            scorer: &scoring::tournament_score,
+            tt: &tt,
+            ordering: &MoveOrdering::new(&game),
+            interrupt: None,
+            stats: &Default::default(),
+            max_turn: 0,
+        };
+        let mut root = MaximizingNode::new(game);
+        root.solve(&ctx, 3, &AlphaBeta::new(i64::MIN, i64::MAX), 1.0);
+
+        let tree = export(&root, 1, 2);
+        assert_eq!(tree.score, root.score.map(|s| s.1));
+        assert_eq!(tree.children.len(), 2);
+        assert_eq!(tree.omitted, root.children.len() - 2);
+        // The best move goes first
+        assert_eq!(tree.children[0].score, tree.score);
+        for min_node in &tree.children {
+            assert!(min_node.children.len() <= 2);
+            for max_node in &min_node.children {
+                assert!(max_node.children.is_empty());
+            }
+        }
+
+        let dot = to_dot(&tree);
+        assert!(dot.starts_with("digraph tree {"));
+        let omitted = dot.matches("more\"").count();
+        assert_eq!(dot.matches(" -> ").count(), count(&tree) - 1 + omitted);
+
+        let json: ExportNode = serde_json::from_str(&to_json(&tree)).unwrap();
+        assert_eq!(json, tree);
+        assert_eq!(count(&export(&root, 3, 4)), count(&export(&root, 5, 4)));
+    }
+}
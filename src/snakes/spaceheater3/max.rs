@@ -1,22 +1,29 @@
 use rayon::prelude::*;
 
-use std::{
-    sync::{
-        atomic::{AtomicBool, AtomicUsize, Ordering},
-        Arc, RwLock,
-    },
-    time::Instant,
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, RwLock,
 };
 
-use crate::{
-    logic::{self, Direction, Game},
-    protocol::ALL_DIRECTIONS,
+use crate::logic::{self, Direction, Game};
+
+use super::{
+    alphabeta::AlphaBeta,
+    min::MinimizingNode,
+    ordering::MoveOrdering,
+    transposition::{Bound, Entry},
+    util::{lost, sensible_moves},
+    SearchContext,
 };
 
-use super::{alphabeta::AlphaBeta, min::MinimizingNode, util::certain_death};
+// Half the width of the first aspiration window, five tiles of floodfill
+// for tournament_score
+const ASPIRATION_WINDOW: i64 = 5_000;
 
 pub struct MaximizingNode {
     pub(super) game: Game,
+    // The enemy moves that led to this position
+    pub(super) enemy_moves: Vec<Direction>,
     pub(super) score: Option<(Direction, i64)>,
     pub(super) children: Vec<MinimizingNode>,
     pub(super) will_die: bool,
@@ -26,31 +33,40 @@ impl MaximizingNode {
     pub fn new(game: Game) -> Self {
         Self {
             game,
+            enemy_moves: vec![],
             score: None,
             children: vec![],
             will_die: false,
         }
     }
 
-    fn update_children(&mut self) {
+    fn update_children(&mut self, best_move: Option<Direction>, ordering: &MoveOrdering) {
         if self.children.len() == 0 {
-            for my_dir in ALL_DIRECTIONS {
-                let mut my_pos = self.game.you.head.neighbour(my_dir);
-                self.game.warp(&mut my_pos);
-                if !certain_death(&self.game, &self.game.you, &my_pos) {
-                    self.children.push(MinimizingNode::new(my_dir));
-                }
+            let mut moves = sensible_moves(&self.game, &self.game.you);
+            ordering.order_moves(&self.game, &mut moves);
+            for my_dir in moves {
+                self.children.push(MinimizingNode::new(my_dir));
             }
         } else {
             self.children.sort_unstable_by(|c1, c2| c1.cmp_scores(c2));
         }
+
+        // The best move found by an earlier search of this position goes first
+        if let Some(idx) = self
+            .children
+            .iter()
+            .position(|c| Some(c.my_move) == best_move)
+        {
+            let best = self.children.remove(idx);
+            self.children.insert(0, best);
+        }
     }
 
     fn check_bounds<S>(&mut self, max_depth: usize, scorer: &S) -> bool
     where
         S: logic::scoring::Scorer,
     {
-        if self.game.you.dead() {
+        if lost(&self.game) {
             if self.score == None {
                 self.score = Some((Direction::Up, scorer.score(&self.game)));
             }
@@ -70,30 +86,40 @@ impl MaximizingNode {
 impl MaximizingNode {
     pub fn solve<S>(
         &mut self,
-        deadline: &Instant,
+        ctx: &SearchContext<'_, S>,
         max_depth: usize,
-        scorer: &S,
         alpha_beta: &AlphaBeta<'_>,
         threads: f32,
     ) -> (Option<(Direction, i64)>, usize)
     where
         S: logic::scoring::Scorer + Sync + Clone + 'static,
     {
-        if Instant::now() > *deadline {
+        if ctx.out_of_time() {
             return (None, 0);
         }
-        if self.check_bounds(max_depth, scorer) {
+        // Scores are wrong when a head-to-head or a forced move is about to
+        // happen, those positions are searched until they're quiet
+        let max_depth = if max_depth == 0 && ctx.extend(&self.game) {
+            1
+        } else {
+            max_depth
+        };
+        if self.check_bounds(max_depth, ctx.scorer) {
             return (self.score.clone(), 1);
         }
-        self.update_children();
 
-        if self.children.len() == 0 {
-            // All paths are certain death, just score this board and return
-            self.game.execute_moves(Direction::Up, &vec![]);
-            let score = scorer.score(&self.game);
-            self.score = Some((Direction::Up, score));
-            return (self.score.clone(), 1);
+        // The root is always searched, so every iteration gets deeper
+        let key = self.game.exact_zobrist();
+        let entry = ctx.tt.get(key);
+        if let Some(entry) = entry.filter(|_| !alpha_beta.is_root()) {
+            let (alpha, beta) = alpha_beta.values();
+            if let Some(score) = entry.cutoff(max_depth, alpha, beta) {
+                self.score = Some((entry.best_move, score));
+                self.will_die = entry.will_die;
+                return (self.score, 1);
+            }
         }
+        self.update_children(entry.map(|e| e.best_move), ctx.ordering);
 
         let (parallel, threads) = if threads > 1f32 {
             (true, threads / self.children.len() as f32)
@@ -103,27 +129,45 @@ impl MaximizingNode {
 
         let game = Arc::new(&self.game);
         let top_score = RwLock::new((Direction::Up, None));
+        let parent_alpha_beta = alpha_beta;
         let alpha_beta = alpha_beta.new_child();
         let total_node_count = AtomicUsize::new(0);
         let will_die = AtomicBool::new(false);
+        let pruned = AtomicBool::new(false);
 
-        let solver = |min_node: &mut MinimizingNode| {
+        let solver = |(idx, min_node): (usize, &mut MinimizingNode)| {
             if alpha_beta.should_be_pruned() {
+                ctx.stats.pruned();
+                pruned.store(true, Ordering::Relaxed);
                 return;
             }
+            ctx.stats.searched();
 
-            let (next_score, node_count) = min_node.solve(
-                game.clone(),
-                deadline,
-                max_depth,
-                scorer,
-                &alpha_beta,
-                threads,
-            );
-            total_node_count.fetch_add(node_count, Ordering::Relaxed);
+            // Principal variation search: the first move is expected to be
+            // the best, the others only have to be proven worse with a null
+            // window. Moves that turn out better than alpha are searched
+            // again with the full window.
+            let (alpha, beta) = alpha_beta.values();
+            let mut next_score = None;
+            if idx > 0 && alpha > i64::MIN && alpha.saturating_add(1) < beta {
+                let scout = alpha_beta.new_child();
+                scout.new_beta_score(alpha + 1);
+                let (score, node_count) =
+                    min_node.solve(game.clone(), ctx, max_depth, &scout, threads);
+                total_node_count.fetch_add(node_count, Ordering::Relaxed);
+                next_score = score.filter(|s| *s <= alpha || *s >= beta);
+            }
+            if next_score.is_none() {
+                let (score, node_count) =
+                    min_node.solve(game.clone(), ctx, max_depth, &alpha_beta, threads);
+                total_node_count.fetch_add(node_count, Ordering::Relaxed);
+                next_score = score;
+            }
 
             if next_score == None {
-                return; // Deadline exceeded
+                // Deadline exceeded, or every enemy move was pruned
+                pruned.store(true, Ordering::Relaxed);
+                return;
             }
 
             let top_score_read = top_score.read().unwrap();
@@ -140,12 +184,17 @@ impl MaximizingNode {
             }
         };
         if parallel {
-            let _res: Vec<()> = self.children.par_iter_mut().map(solver).collect();
+            let _res: Vec<()> = self
+                .children
+                .par_iter_mut()
+                .enumerate()
+                .map(solver)
+                .collect();
         } else {
-            let _res: Vec<()> = self.children.iter_mut().map(solver).collect();
+            let _res: Vec<()> = self.children.iter_mut().enumerate().map(solver).collect();
         }
 
-        if Instant::now() > *deadline {
+        if ctx.out_of_time() {
             // deadline exceeded
             return (None, total_node_count.load(Ordering::Relaxed));
         }
@@ -153,9 +202,96 @@ impl MaximizingNode {
         let (top_move, top_score) = top_score.read().unwrap().clone();
         self.score = top_score.map(|s| (top_move, s));
         self.will_die = will_die.load(Ordering::Relaxed);
+        if let Some(score) = top_score {
+            // Windows only get smaller, children that were cut off can't
+            // have produced a score inside the final window. Siblings
+            // searched in parallel can close the window of an ancestor
+            // halfway, then the children that were skipped could still score
+            // higher and the score is only a lower bound.
+            let (alpha, beta) = parent_alpha_beta.values();
+            let pruned = pruned.load(Ordering::Relaxed);
+            let bound = if score <= alpha && !pruned {
+                Some(Bound::Upper)
+            } else if score >= beta {
+                ctx.ordering.our_cutoff(&self.game, top_move, max_depth);
+                Some(Bound::Lower)
+            } else if !pruned {
+                Some(Bound::Exact)
+            } else {
+                None
+            };
+            if let Some(bound) = bound {
+                ctx.tt.put(
+                    key,
+                    Entry {
+                        bound,
+                        depth: max_depth.min(u8::MAX as usize) as u8,
+                        score,
+                        best_move: top_move,
+                        will_die: self.will_die,
+                    },
+                );
+            }
+        }
         return (self.score, total_node_count.load(Ordering::Relaxed));
     }
 
+    /// solve_aspiration searches the root with a window around guess, the
+    /// score of a shallower search. When the score falls outside the window,
+    /// the window is widened on that side and the root is searched again.
+    pub fn solve_aspiration<S>(
+        &mut self,
+        ctx: &SearchContext<'_, S>,
+        max_depth: usize,
+        guess: Option<i64>,
+        threads: f32,
+    ) -> (Option<(Direction, i64)>, usize)
+    where
+        S: logic::scoring::Scorer + Sync + Clone + 'static,
+    {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = match guess {
+            Some(score) => (score.saturating_sub(delta), score.saturating_add(delta)),
+            None => (i64::MIN, i64::MAX),
+        };
+        let mut total_node_count = 0;
+        loop {
+            let (res, node_count) =
+                self.solve(ctx, max_depth, &AlphaBeta::new(alpha, beta), threads);
+            total_node_count += node_count;
+            delta = delta.saturating_mul(4);
+            match res {
+                Some((_, score)) if score <= alpha && alpha > i64::MIN => {
+                    alpha = score.saturating_sub(delta);
+                }
+                Some((_, score)) if score >= beta && beta < i64::MAX => {
+                    beta = score.saturating_add(delta);
+                }
+                _ => return (res, total_node_count),
+            }
+        }
+    }
+
+    /// principal_variation lists the moves the search expects, our best move
+    /// and the enemy moves that are worst for us, for every turn in the tree.
+    pub fn principal_variation(&self) -> Vec<(Direction, Vec<Direction>)> {
+        let mut pv = vec![];
+        let mut node = self;
+        while let Some(min_node) = node.score.and_then(|(best_move, _)| {
+            node.children
+                .iter()
+                .find(|c| c.my_move == best_move && c.score.is_some())
+        }) {
+            let (enemy_moves, next) = min_node.worst_reply();
+            pv.push((min_node.my_move, enemy_moves));
+            match next {
+                Some(next) => node = next,
+                None => break,
+            }
+        }
+        pv
+    }
+
     pub fn cmp_scores(&self, other: &Self) -> std::cmp::Ordering {
         let self_score = self.score.map(|s| s.1).unwrap_or(i64::MAX);
         let other_score = other.score.map(|s| s.1).unwrap_or(i64::MAX);
@@ -186,3 +322,124 @@ impl std::fmt::Display for MaximizingNode {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{
+        logic::{notation::parse_game, scoring},
+        snakes::spaceheater3::{transposition::TranspositionTable, SearchMode},
+    };
+
+    fn solve(text: &str, depth: usize, extensions: usize) -> MaximizingNode {
+        let game = parse_game(text).unwrap();
+        let max_turn = game.turn + depth + extensions;
+        let tt = TranspositionTable::new(1 << 12);
+        let ordering = MoveOrdering::new(&game);
+        let ctx = SearchContext {
+            deadline: Instant::now() + Duration::from_secs(600),
+            mode: SearchMode::Paranoid,
+            scorer: &scoring::tournament_score,
+            tt: &tt,
+            ordering: &ordering,
+            interrupt: None,
+            stats: &Default::default(),
+            max_turn,
+        };
+        let mut root = MaximizingNode::new(game);
+        root.solve(&ctx, depth, &AlphaBeta::new(i64::MIN, i64::MAX), 1.0);
+        root
+    }
+
+    #[test]
+    fn lost_positions() {
+        // Every move is certain death for both of us
+        let trade = solve(
+            "
+A: length=3
+B: length=3
+A # . # B
+^ . . . ^
+",
+            2,
+            0,
+        );
+        // B gets away
+        let alone = solve(
+            "
+A: length=3
+B: length=3
+A # . . B
+^ . . . ^
+",
+            2,
+            0,
+        );
+        assert!(trade.will_die && alone.will_die);
+        assert_eq!(trade.children.len(), 4);
+        let (trade, alone) = (trade.score.unwrap().1, alone.score.unwrap().1);
+        assert_eq!(trade - alone, 10_000_000);
+    }
+
+    #[test]
+    fn horizon_extension() {
+        // Up leads into a corridor, where B is waiting for us
+        let position = "
+A: length=3
+B: length=4
+. . . . .
+. . . B .
+# . # ^ .
+. A . . .
+. ^ . . .
+";
+        let score_of_up = |root: &MaximizingNode| {
+            let up = root.children.iter().find(|c| c.my_move == Direction::Up);
+            up.unwrap().score.unwrap()
+        };
+        let flat = solve(position, 1, 0);
+        let extended = solve(position, 1, 2);
+        assert!(score_of_up(&extended) < score_of_up(&flat));
+        assert_ne!(extended.score.unwrap().0, Direction::Up);
+    }
+
+    #[test]
+    fn aspiration_windows() {
+        let game = parse_game(
+            "
+A: length=3
+B: length=4
+. . . . . . .
+. . . . + . .
+. A . . . B .
+. ^ . # . ^ .
+. ^ . . . ^ .
+",
+        )
+        .unwrap();
+        let search = |guess: Option<i64>| {
+            let tt = TranspositionTable::new(1 << 12);
+            let ordering = MoveOrdering::new(&game);
+            let ctx = SearchContext {
+                deadline: Instant::now() + Duration::from_secs(600),
+                mode: SearchMode::Paranoid,
+                scorer: &scoring::tournament_score,
+                tt: &tt,
+                ordering: &ordering,
+                interrupt: None,
+                stats: &Default::default(),
+                max_turn: 0,
+            };
+            let mut root = MaximizingNode::new(game.clone());
+            root.solve_aspiration(&ctx, 3, guess, 1.0).0.unwrap().1
+        };
+
+        // Guesses that are far off are corrected by searching again
+        let expected = search(None);
+        for guess in [expected, expected - 1_000_000, expected + 1_000_000] {
+            assert_eq!(search(Some(guess)), expected);
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use rayon::prelude::*;
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use crate::logic::{
+    scoring::{score_of, MultiScorer},
+    Direction, Game,
+};
+
+use super::util::sensible_moves;
+
+/// MaxN searches the game assuming every snake picks the move that is best
+/// for itself, instead of the move that is worst for us like the paranoid
+/// search of MaximizingNode does. That makes more sense with many snakes on
+/// the board, where most snakes are busy with each other.
+///
+/// Snakes pick their moves one after the other, you first, and the moves are
+/// made when all snakes picked one. Later snakes see the moves picked before
+/// them, which is a little pessimistic for us.
+pub struct MaxN<'a, M> {
+    deadline: Instant,
+    scorer: &'a M,
+    // Bound on the sum of the scores, for shallow pruning
+    max_sum: Option<i64>,
+    node_count: AtomicUsize,
+}
+
+impl<'a, M> MaxN<'a, M>
+where
+    M: MultiScorer + Sync,
+{
+    pub fn new(game: &Game, scorer: &'a M, deadline: Instant) -> Self {
+        Self {
+            deadline,
+            scorer,
+            max_sum: scorer.max_sum(game),
+            node_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// solve searches max_depth turns deep and returns our best move with the
+    /// scores it leads to, or None if the deadline passed.
+    pub fn solve(&self, game: &Game, max_depth: usize) -> Option<(Direction, Vec<i64>)> {
+        if game.you.dead() || max_depth == 0 {
+            return None;
+        }
+        let you = game.you.id;
+        let results: Vec<_> = sensible_moves(game, &game.you)
+            .into_par_iter()
+            .map(|dir| {
+                let mut game = game.clone();
+                self.choose(&mut game, max_depth, &mut vec![dir], None)
+                    .map(|scores| (dir, scores))
+            })
+            .collect();
+
+        let mut best: Option<(Direction, Vec<i64>)> = None;
+        for (dir, scores) in results.into_iter().collect::<Option<Vec<_>>>()? {
+            if best
+                .as_ref()
+                .is_none_or(|(_, b)| score_of(&scores, you) > score_of(b, you))
+            {
+                best = Some((dir, scores));
+            }
+        }
+        best
+    }
+
+    /// node_count is the number of positions scored so far.
+    pub fn node_count(&self) -> usize {
+        self.node_count.load(Ordering::Relaxed)
+    }
+
+    // Searches the game after all moves of the previous turn were made.
+    // parent is the snake that picked the last move, with its best score so
+    // far.
+    fn turn(&self, game: &mut Game, depth: usize, parent: Option<(u8, i64)>) -> Option<Vec<i64>> {
+        if Instant::now() > self.deadline {
+            return None;
+        }
+        if depth == 0 || game.you.dead() {
+            self.node_count.fetch_add(1, Ordering::Relaxed);
+            return Some(self.scorer.scores(game));
+        }
+        self.choose(
+            game,
+            depth,
+            &mut Vec::with_capacity(game.others.len() + 1),
+            parent,
+        )
+    }
+
+    // Picks the best move for the next snake without a move this turn.
+    fn choose(
+        &self,
+        game: &mut Game,
+        depth: usize,
+        moves: &mut Vec<Direction>,
+        parent: Option<(u8, i64)>,
+    ) -> Option<Vec<i64>> {
+        let snake = if moves.is_empty() {
+            &game.you
+        } else {
+            &game.others[moves.len() - 1]
+        };
+        let id = snake.id;
+        let mut best: Option<Vec<i64>> = None;
+        for dir in sensible_moves(game, snake) {
+            let bound = best.as_ref().map(|b| (id, score_of(b, id)));
+            moves.push(dir);
+            let res = if moves.len() == game.others.len() + 1 {
+                let undo = game.make_move(moves[0], &moves[1..]);
+                let res = self.turn(game, depth - 1, bound);
+                game.unmake_move(undo);
+                res
+            } else {
+                self.choose(game, depth, moves, bound)
+            };
+            moves.pop();
+
+            let scores = res?;
+            if best
+                .as_ref()
+                .is_none_or(|b| score_of(&scores, id) > score_of(b, id))
+            {
+                best = Some(scores);
+            }
+
+            // Shallow pruning: the scores add up to at most max_sum, so once
+            // this snake gets max_sum - x, the parent gets at most x here. If
+            // the parent already has x elsewhere, it won't pick this move.
+            if let (Some(max_sum), Some((_, parent_best))) = (self.max_sum, parent) {
+                let score = score_of(best.as_ref().unwrap(), id);
+                if score >= max_sum.saturating_sub(parent_best) {
+                    break;
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::logic::{
+        notation::parse_game,
+        scoring::{self, Perspectives, TileShares},
+    };
+
+    fn deadline() -> Instant {
+        Instant::now() + Duration::from_secs(600)
+    }
+
+    // Max-n without pruning, to compare against
+    struct Unbounded;
+
+    impl MultiScorer for Unbounded {
+        fn scores(&self, game: &Game) -> Vec<i64> {
+            TileShares.scores(game)
+        }
+    }
+
+    #[test]
+    fn shallow_pruning() {
+        // The scores add up to at most 10 tiles
+        let game = parse_game(
+            "
+A: length=1
+B: length=1
+. . # .
+A . # B
+. . . .
+",
+        )
+        .unwrap();
+        for depth in 2..=4 {
+            let full = MaxN::new(&game, &Unbounded, deadline());
+            let pruned = MaxN::new(&game, &TileShares, deadline());
+            let expected = full.solve(&game, depth).unwrap();
+            assert_eq!(pruned.solve(&game, depth).unwrap(), expected);
+            assert!(pruned.node_count() < full.node_count());
+        }
+    }
+
+    #[test]
+    fn others_look_after_themselves() {
+        // Paranoid search expects B to take us out head to head, but B
+        // would die as well
+        let game = parse_game(
+            "
+A: length=3
+B: length=3
+# . . . .
+A . B < .
+^ # . . .
+",
+        )
+        .unwrap();
+        let search = MaxN::new(&game, &Perspectives(scoring::turns_survived), deadline());
+        let (dir, scores) = search.solve(&game, 1).unwrap();
+        assert_eq!(dir, Direction::Right);
+        assert_eq!(score_of(&scores, 0), 1);
+        assert_eq!(score_of(&scores, 1), 1);
+    }
+}
@@ -1,22 +1,28 @@
 use rayon::prelude::*;
 
-use std::{
-    sync::{
-        atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering},
-        Arc,
-    },
-    time::Instant,
+use std::sync::{
+    atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering},
+    Arc, Mutex,
 };
 
 use crate::logic::{self, Direction, Game};
 
-use super::{alphabeta::AlphaBeta, max::MaximizingNode, util::all_sensible_enemy_moves};
+use super::{
+    alphabeta::AlphaBeta,
+    max::MaximizingNode,
+    ordering::MoveOrdering,
+    util::{all_sensible_enemy_moves, best_reply_enemy_moves, lost},
+    SearchContext, SearchMode,
+};
 
 pub struct MinimizingNode {
     pub my_move: Direction,
     pub(super) score: Option<i64>,
     pub(super) children: Vec<MaximizingNode>,
     pub(super) will_die: bool,
+    // Scores of the enemy moves when this node was solved without children,
+    // used to order the children once they are created.
+    pub(super) leaf_scores: Vec<(i64, Vec<Direction>)>,
 }
 
 impl MinimizingNode {
@@ -26,21 +32,51 @@ impl MinimizingNode {
             score: None,
             children: vec![],
             will_die: false,
+            leaf_scores: vec![],
         }
     }
 
-    fn update_children(&mut self, game: &Game) {
+    fn update_children(&mut self, game: &Game, mode: SearchMode, ordering: &MoveOrdering) {
         if self.children.len() == 0 {
-            for combo in all_sensible_enemy_moves(game) {
+            let combos = if self.leaf_scores.is_empty() {
+                let mut combos = enemy_moves(game, mode);
+                ordering.order_combos(game, &mut combos);
+                combos
+            } else {
+                let mut scored = std::mem::take(&mut self.leaf_scores);
+                scored.sort_by_key(|(score, _)| *score);
+                scored.into_iter().map(|(_, combo)| combo).collect()
+            };
+            for combo in combos {
                 let mut game = game.clone();
                 game.execute_moves(self.my_move, &combo);
-                self.children.push(MaximizingNode::new(game));
+                let mut child = MaximizingNode::new(game);
+                child.enemy_moves = combo;
+                self.children.push(child);
             }
         } else {
             self.children.sort_unstable_by(|c1, c2| c1.cmp_scores(c2));
         }
     }
 
+    /// worst_reply returns the enemy moves that are worst for us, with the
+    /// node they lead to if it was created.
+    pub fn worst_reply(&self) -> (Vec<Direction>, Option<&MaximizingNode>) {
+        let child = self
+            .children
+            .iter()
+            .filter_map(|c| c.score.map(|s| (s.1, c)))
+            .min_by_key(|(score, _)| *score);
+        if let Some((_, child)) = child {
+            return (child.enemy_moves.clone(), Some(child));
+        }
+        let leaf = self.leaf_scores.iter().min_by_key(|(score, _)| *score);
+        (
+            leaf.map(|(_, combo)| combo.clone()).unwrap_or_default(),
+            None,
+        )
+    }
+
     pub fn cmp_scores(&self, other: &Self) -> std::cmp::Ordering {
         let self_score = self.score.unwrap_or(i64::MIN);
         let other_score = other.score.unwrap_or(i64::MIN);
@@ -52,9 +88,8 @@ impl MinimizingNode {
     pub fn solve<S>(
         &mut self,
         game: Arc<&Game>,
-        deadline: &Instant,
+        ctx: &SearchContext<'_, S>,
         max_depth: usize,
-        scorer: &S,
         alpha_beta: &AlphaBeta<'_>,
         threads: f32,
     ) -> (Option<i64>, usize)
@@ -63,7 +98,11 @@ impl MinimizingNode {
     {
         let game = *game.as_ref();
 
-        self.update_children(game);
+        if max_depth == 1 && self.children.is_empty() {
+            return self.solve_leaves(game, ctx, alpha_beta, threads > 1f32);
+        }
+
+        self.update_children(game, ctx.mode, ctx.ordering);
         let (parallel, threads) = if threads > 1f32 {
             (true, threads / self.children.len() as f32)
         } else {
@@ -72,16 +111,19 @@ impl MinimizingNode {
 
         let min_score = AtomicI64::new(i64::MAX);
         let will_die = AtomicBool::new(false);
+        let best_combo = Mutex::new((i64::MAX, vec![]));
+        let parent_alpha_beta = alpha_beta;
         let alpha_beta = alpha_beta.new_child();
         let total_node_count = AtomicUsize::new(0);
 
         let solver = |max_node: &mut MaximizingNode| {
             if alpha_beta.should_be_pruned() {
+                ctx.stats.pruned();
                 return;
             }
+            ctx.stats.searched();
 
-            let (next_score, node_count) =
-                max_node.solve(deadline, max_depth - 1, scorer, &alpha_beta, threads);
+            let (next_score, node_count) = max_node.solve(ctx, max_depth - 1, &alpha_beta, threads);
 
             let next_score = if let Some(s) = next_score {
                 s.1
@@ -93,6 +135,7 @@ impl MinimizingNode {
             if min_score.fetch_min(next_score, Ordering::Relaxed) > next_score {
                 will_die.store(max_node.will_die, Ordering::Relaxed);
                 alpha_beta.new_beta_score(next_score);
+                update_best_combo(&best_combo, next_score, &max_node.enemy_moves);
             }
         };
 
@@ -102,7 +145,7 @@ impl MinimizingNode {
             let _res: Vec<()> = self.children.iter_mut().map(solver).collect();
         }
 
-        if Instant::now() > *deadline {
+        if ctx.out_of_time() {
             // deadline exceeded
             return (None, total_node_count.load(Ordering::Relaxed));
         }
@@ -115,10 +158,124 @@ impl MinimizingNode {
         };
         self.will_die = will_die.load(Ordering::Relaxed);
         self.score = min_score;
+        record_cutoff(game, ctx, parent_alpha_beta, best_combo, max_depth);
         (min_score, total_node_count.load(Ordering::Relaxed))
     }
 }
 
+impl MinimizingNode {
+    /// solve_leaves scores the game after every enemy move, without creating
+    /// child nodes. Instead of cloning the game for every move, a single
+    /// copy per thread is updated with Game::make_move and unmake_move.
+    fn solve_leaves<S>(
+        &mut self,
+        game: &Game,
+        ctx: &SearchContext<'_, S>,
+        alpha_beta: &AlphaBeta<'_>,
+        parallel: bool,
+    ) -> (Option<i64>, usize)
+    where
+        S: logic::scoring::Scorer + Sync + Clone + 'static,
+    {
+        let mut combos = enemy_moves(game, ctx.mode);
+        ctx.ordering.order_combos(game, &mut combos);
+        let min_score = AtomicI64::new(i64::MAX);
+        let will_die = AtomicBool::new(false);
+        let best_combo = Mutex::new((i64::MAX, vec![]));
+        let parent_alpha_beta = alpha_beta;
+        let alpha_beta = alpha_beta.new_child();
+        let total_node_count = AtomicUsize::new(0);
+        let my_move = self.my_move;
+
+        let solver = |state: &mut Game, combo: &Vec<Direction>| {
+            if ctx.out_of_time() {
+                return i64::MAX;
+            }
+            if alpha_beta.should_be_pruned() {
+                ctx.stats.pruned();
+                return i64::MAX;
+            }
+            ctx.stats.searched();
+
+            let undo = state.make_move(my_move, combo);
+            let (score, died, node_count) = if ctx.extend(state) {
+                let mut max_node = MaximizingNode::new(state.clone());
+                match max_node.solve(ctx, 0, &alpha_beta, 1.0) {
+                    (Some((_, score)), node_count) => (score, max_node.will_die, node_count),
+                    (None, _) => (i64::MAX, false, 0), // Deadline exceeded
+                }
+            } else {
+                (ctx.scorer.score(state), lost(state), 1)
+            };
+            state.unmake_move(undo);
+
+            total_node_count.fetch_add(node_count, Ordering::Relaxed);
+            if min_score.fetch_min(score, Ordering::Relaxed) > score {
+                will_die.store(died, Ordering::Relaxed);
+                alpha_beta.new_beta_score(score);
+                update_best_combo(&best_combo, score, combo);
+            }
+            score
+        };
+
+        let scores: Vec<i64> = if parallel {
+            combos
+                .par_iter()
+                .map_init(|| game.clone(), solver)
+                .collect()
+        } else {
+            let mut state = game.clone();
+            combos.iter().map(|c| solver(&mut state, c)).collect()
+        };
+        self.leaf_scores = scores.into_iter().zip(combos).collect();
+
+        if ctx.out_of_time() {
+            // deadline exceeded
+            return (None, total_node_count.load(Ordering::Relaxed));
+        }
+
+        let min_score = min_score.load(Ordering::Relaxed);
+        let min_score = if min_score == i64::MAX {
+            None
+        } else {
+            Some(min_score)
+        };
+        self.will_die = will_die.load(Ordering::Relaxed);
+        self.score = min_score;
+        record_cutoff(game, ctx, parent_alpha_beta, best_combo, 1);
+        (min_score, total_node_count.load(Ordering::Relaxed))
+    }
+}
+
+fn update_best_combo(best: &Mutex<(i64, Vec<Direction>)>, score: i64, combo: &[Direction]) {
+    let mut best = best.lock().unwrap();
+    if score < best.0 {
+        *best = (score, combo.to_vec());
+    }
+}
+
+// Enemy moves that leave us less than we already have elsewhere are
+// remembered, to search them first in other positions
+fn record_cutoff<S>(
+    game: &Game,
+    ctx: &SearchContext<'_, S>,
+    alpha_beta: &AlphaBeta<'_>,
+    best_combo: Mutex<(i64, Vec<Direction>)>,
+    depth: usize,
+) {
+    let (score, combo) = best_combo.into_inner().unwrap();
+    if score != i64::MAX && score <= alpha_beta.values().0 {
+        ctx.ordering.their_cutoff(game, &combo, depth);
+    }
+}
+
+fn enemy_moves(game: &Game, mode: SearchMode) -> Vec<Vec<Direction>> {
+    match mode {
+        SearchMode::BestReply => best_reply_enemy_moves(game),
+        _ => all_sensible_enemy_moves(game),
+    }
+}
+
 impl std::fmt::Display for MinimizingNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if let Some(min_choice) =
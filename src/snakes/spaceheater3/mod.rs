@@ -1,28 +1,106 @@
 use crate::{
     log,
-    logic::{self, scoring, Game},
+    logic::{
+        self,
+        scoring::{score_of, Perspectives},
+        Game,
+    },
     protocol::{self, Customizations, Direction},
-    snakes::{spaceheater3::max::MaximizingNode, Salami},
+    snakes::spaceheater3::max::MaximizingNode,
     util::thread_count,
     Battlesnake,
 };
+use serde::{Deserialize, Serialize};
 use std::{
     cmp,
-    ops::Add,
-    sync::mpsc::channel,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::channel,
+        Arc, Mutex, OnceLock,
+    },
     thread,
-    time::{Duration, Instant},
+    time::Instant,
 };
 
 pub mod alphabeta;
+pub mod export;
 pub mod max;
+pub mod maxn;
 pub mod min;
+pub mod ordering;
+pub mod report;
+pub mod timing;
+pub mod transposition;
 pub mod util;
+pub mod worker;
 
 pub const DEFAULT_COLOR: &str = "#b54d47";
 pub const DEFAULT_HEAD: &str = "scarf";
 pub const DEFAULT_TAIL: &str = "rocket";
-const LATENCY_MARGIN: Duration = Duration::from_millis(115);
+/// MAX_N_SNAKES is the number of snakes from which on the game is searched
+/// with max-n instead of paranoid minimax. With this many snakes, assuming
+/// they all go after us makes us too timid.
+pub const MAX_N_SNAKES: usize = 4;
+/// BEST_REPLY_SNAKES is the number of snakes from which on the game is
+/// searched with Best-Reply Search, every combination of enemy moves is too
+/// many for max-n and paranoid search.
+pub const BEST_REPLY_SNAKES: usize = 6;
+/// DEFAULT_EXTENSIONS is how many turns the search may go past its depth,
+/// to resolve head-to-heads and forced moves at the horizon.
+pub const DEFAULT_EXTENSIONS: usize = 4;
+
+/// SearchMode is how the moves of the other snakes are searched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchMode {
+    // The others pick the combination of moves that is worst for us
+    Paranoid,
+    // Like Paranoid, but only the one enemy that hurts us most picks its
+    // move, the others play a default move
+    BestReply,
+    // Every snake picks the move that is best for itself
+    MaxN,
+}
+
+impl SearchMode {
+    pub fn for_game(game: &Game) -> Self {
+        match game.others.len() + 1 {
+            n if n >= BEST_REPLY_SNAKES => SearchMode::BestReply,
+            n if n >= MAX_N_SNAKES => SearchMode::MaxN,
+            _ => SearchMode::Paranoid,
+        }
+    }
+}
+
+/// SearchContext holds what every node of a search needs.
+pub struct SearchContext<'a, S> {
+    pub deadline: Instant,
+    // Paranoid or BestReply, max-n doesn't use the tree
+    pub mode: SearchMode,
+    pub scorer: &'a S,
+    pub tt: &'a transposition::TranspositionTable,
+    pub ordering: &'a ordering::MoveOrdering,
+    // Stops the search before the deadline when set
+    pub interrupt: Option<&'a AtomicBool>,
+    pub stats: &'a report::SearchStats,
+    // Positions at the horizon before this turn are searched further when
+    // they aren't quiet
+    pub max_turn: usize,
+}
+
+impl<S> SearchContext<'_, S> {
+    #[inline(always)]
+    pub fn out_of_time(&self) -> bool {
+        Instant::now() > self.deadline || self.interrupt.is_some_and(|i| i.load(Ordering::Relaxed))
+    }
+
+    /// extend is true if a position at the horizon should be searched one
+    /// turn deeper.
+    #[inline(always)]
+    pub fn extend(&self, game: &Game) -> bool {
+        game.turn < self.max_turn && !util::lost(game) && !util::quiet(game)
+    }
+}
 
 pub struct Spaceheater3<S>
 where
@@ -30,6 +108,16 @@ where
 {
     scorer: S,
     customizations: Customizations,
+    // Shared by all searches, positions often repeat in the next turn.
+    // Allocated on the first move, many snakes are created but never play.
+    tt: OnceLock<Arc<transposition::TranspositionTable>>,
+    // Background searches of the active games, by game id
+    workers: Mutex<HashMap<String, Arc<worker::Worker>>>,
+    // Turns the search may go past its depth
+    extensions: usize,
+    clock: timing::TimeManager,
+    // Report of the last search, by game id
+    reports: Mutex<HashMap<String, report::SearchReport>>,
 }
 
 impl<S> Spaceheater3<S>
@@ -43,25 +131,76 @@ where
                 color: DEFAULT_COLOR.into(),
                 head: DEFAULT_HEAD.into(),
                 tail: DEFAULT_TAIL.into(),
+                ..Default::default()
             }),
+            tt: OnceLock::new(),
+            workers: Mutex::new(HashMap::new()),
+            extensions: DEFAULT_EXTENSIONS,
+            clock: Default::default(),
+            reports: Mutex::new(HashMap::new()),
         }
     }
 
-    pub fn solve(
+    /// with_extensions sets how many turns the search may go past its depth
+    /// to resolve head-to-heads, 0 disables the extension.
+    pub fn with_extensions(mut self, extensions: usize) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    fn tt(&self) -> Arc<transposition::TranspositionTable> {
+        self.tt.get_or_init(Default::default).clone()
+    }
+
+    /// worker returns the background search of a game, and starts one if
+    /// there is none or it stopped after being idle for too long.
+    fn worker(&self, game_id: &str, game: &Game) -> Arc<worker::Worker> {
+        let mut workers = self.workers.lock().unwrap();
+        if let Some(w) = workers.get(game_id) {
+            if !w.finished() {
+                return w.clone();
+            }
+        }
+        workers.retain(|_, w| !w.finished());
+        let w = Arc::new(worker::Worker::start(
+            game.clone(),
+            self.scorer.clone(),
+            self.tt(),
+            self.extensions,
+        ));
+        workers.insert(game_id.to_string(), w.clone());
+        w
+    }
+
+    pub fn solve(&self, game: Game, deadline: &Instant, max_depth: usize) -> report::SearchReport {
+        self.solve_tree(MaximizingNode::new(game), deadline, max_depth)
+            .0
+    }
+
+    /// solve_tree searches the tree until the deadline, and returns a report
+    /// with the best move, and the tree, so it can be searched further.
+    pub fn solve_tree(
         &self,
-        game: Game,
+        mut root: MaximizingNode,
         deadline: &Instant,
         max_depth: usize,
-    ) -> Option<(Direction, i64)> {
+    ) -> (report::SearchReport, MaximizingNode) {
+        let game = root.game.clone();
+        let mode = SearchMode::for_game(&game);
+        if mode == SearchMode::MaxN {
+            let report = solve_max_n(&game, self.scorer.clone(), deadline, max_depth);
+            return (report, root);
+        }
         let enemy_count = game.others.len();
         let _turn = game.turn;
 
-        let base_depth = match enemy_count {
-            0 => 5,
-            1 => 3,
-            2 => 2,
-            3 => 2,
-            4 => 2,
+        let base_depth = match (mode, enemy_count) {
+            (SearchMode::BestReply, _) => 2,
+            (_, 0) => 5,
+            (_, 1) => 3,
+            (_, 2) => 2,
+            (_, 3) => 2,
+            (_, 4) => 2,
             _ => 1,
         };
         let _start = Instant::now();
@@ -80,11 +219,17 @@ where
         let scorer = self.scorer.clone();
         let deadline = deadline.clone();
         let game = game.clone();
+        let tt = self.tt();
+        let extensions = self.extensions;
+        tt.new_search();
         thread::spawn(move || {
-            let mut root = MaximizingNode::new(game.clone());
-            let mut best_score = None;
+            // Killers and history are shared by all depths
+            let ordering = ordering::MoveOrdering::new(&game);
+            let stats = report::SearchStats::default();
+            let mut report = report::SearchReport::new(&game, mode);
             let mut last_score = None;
-            let mut _total_node_count = 0;
+            // Depths the background search already completed only hit the
+            // transposition table
             for current_depth in base_depth..max_depth {
                 log!(
                     "turn {}: {}ms: starting depth {}",
@@ -92,14 +237,26 @@ where
                     _start.elapsed().as_millis(),
                     current_depth,
                 );
-                let (res, node_count) = root.solve(
-                    &deadline,
-                    current_depth,
-                    &scorer,
-                    &alphabeta::AlphaBeta::new(i64::MIN, i64::MAX),
-                    thread_count() as f32,
-                );
-                _total_node_count += node_count;
+                let depth_start = Instant::now();
+                let ctx = SearchContext {
+                    deadline,
+                    mode,
+                    scorer: &scorer,
+                    tt: &tt,
+                    ordering: &ordering,
+                    interrupt: None,
+                    stats: &stats,
+                    max_turn: game.turn + current_depth + extensions,
+                };
+                // The score of the last depth is a good guess for this one
+                let (res, node_count) =
+                    root.solve_aspiration(&ctx, current_depth, last_score, thread_count() as f32);
+                report.add_depth(report::DepthReport {
+                    depth: current_depth,
+                    node_count,
+                    millis: depth_start.elapsed().as_millis() as u64,
+                    best: res,
+                });
                 log!(
                     "complete min-max path for depth {}:\n{}",
                     current_depth,
@@ -108,13 +265,13 @@ where
 
                 match &res {
                     Some((_dir, _score)) => {
-                        best_score = res.clone();
+                        report.best = res;
                         log!(
                             "turn {}: {}ms: completed depth {}, tree has {} nodes: {} {}",
                             _turn,
                             _start.elapsed().as_millis(),
                             current_depth,
-                            _total_node_count,
+                            report.node_count(),
                             _dir,
                             _score,
                         );
@@ -129,57 +286,107 @@ where
                         break;
                     }
                 }
-                if last_score == best_score.as_ref().map(|s| s.1.clone()) {
+                if last_score == report.best.map(|s| s.1) {
                     log!(
                         "turn {}: {}ms: tree completed at depth {} after {} nodes",
                         _turn,
                         _start.elapsed().as_millis(),
                         current_depth - 1,
-                        _total_node_count,
+                        report.node_count(),
                     );
                     break;
                 }
-                last_score = best_score.as_ref().map(|s| s.1.clone());
+                last_score = report.best.map(|s| s.1);
                 if root.will_die {
                     break;
                 }
             }
+            report.move_scores = root.children.iter().map(|c| (c.my_move, c.score)).collect();
+            report.principal_variation = root.principal_variation();
+            report.prune_rate = stats.prune_rate();
 
             if root.will_die {
-                let salami = Salami::new(scoring::turns_survived, None);
-                println!("minimax thinks we will die, go into avoidance mode (monte carlo)");
-                let deadline = deadline.min(Instant::now().add(Duration::from_millis(125)));
-                best_score = Some(salami.solve(game, &deadline));
+                // The others might have better things to do than to kill us
+                log!(
+                    "turn {}: {}ms: minimax thinks we will die, switching to max-n",
+                    _turn,
+                    _start.elapsed().as_millis()
+                );
+                report.fallback = Some(SearchMode::MaxN);
+                if let Some(res) = solve_max_n(&game, scorer, &deadline, max_depth).best {
+                    report.best = Some(res);
+                }
             }
 
-            let _ = tx.send(best_score);
             let _statm = procinfo::pid::statm_self().unwrap();
             log!(
-                "turn {}: {}ms / {} MB: returning {}",
+                "turn {}: {}ms / {} MB: {}",
                 _turn,
                 _start.elapsed().as_millis(),
                 _statm.size * 4096 / 1024 / 1024,
-                best_score
-                    .clone()
-                    .map(|v| v.0.to_string())
-                    .unwrap_or("None".to_string())
+                report
             );
-            log!("scores for moves: ");
-            for _c in &root.children {
-                log!(
-                    "{}: {}",
-                    _c.my_move,
-                    _c.score
-                        .map(|s| s.to_string())
-                        .unwrap_or("pruned".to_string())
-                )
-            }
+            let _ = tx.send((report, root));
         });
 
         rx.recv().unwrap()
     }
 }
 
+/// solve_max_n searches the game with max-n until the deadline, and returns
+/// a report with the best move and our score. Every snake is scored with our
+/// scorer, which doesn't bound the sum of the scores, so there is no shallow
+/// pruning.
+fn solve_max_n<S>(
+    game: &Game,
+    scorer: S,
+    deadline: &Instant,
+    max_depth: usize,
+) -> report::SearchReport
+where
+    S: logic::scoring::Scorer + Sync,
+{
+    let scorer = Perspectives(scorer);
+    let search = maxn::MaxN::new(game, &scorer, *deadline);
+    let mut report = report::SearchReport::new(game, SearchMode::MaxN);
+    let (mut total_node_count, mut last_node_count) = (0, 0);
+    for depth in 1..=max_depth {
+        let depth_start = Instant::now();
+        let res = search.solve(game, depth);
+        let node_count = search.node_count() - total_node_count;
+        total_node_count += node_count;
+        let best = res
+            .as_ref()
+            .map(|(dir, scores)| (*dir, score_of(scores, game.you.id)));
+        report.add_depth(report::DepthReport {
+            depth,
+            node_count,
+            millis: depth_start.elapsed().as_millis() as u64,
+            best,
+        });
+        let (dir, _scores) = match res {
+            Some(res) => res,
+            None => break,
+        };
+        report.best = best;
+        report.principal_variation = vec![(dir, vec![])];
+        log!(
+            "turn {}: max-n completed depth {} after {} nodes: {} {:?}",
+            game.turn,
+            depth,
+            node_count,
+            dir,
+            _scores,
+        );
+        if node_count == last_node_count {
+            // Every line ended before this depth, deeper searches won't change anything
+            break;
+        }
+        last_node_count = node_count;
+    }
+    report
+}
+
 impl<S> Battlesnake for Spaceheater3<S>
 where
     S: logic::scoring::Scorer + Send + Sync + Clone + 'static,
@@ -195,33 +402,76 @@ where
         }
     }
 
-    fn start(&self, _: &crate::protocol::Request) -> Result<(), String> {
+    fn start(&self, req: &crate::protocol::Request) -> Result<(), String> {
+        // Start searching before the first move request comes in
+        let game = Game::try_from(req)?;
+        if SearchMode::for_game(&game) != SearchMode::MaxN {
+            self.worker(&req.game.id, &game);
+        }
         Ok(())
     }
 
-    fn end(&self, _: &crate::protocol::Request) -> Result<(), String> {
+    fn end(&self, req: &crate::protocol::Request) -> Result<(), String> {
+        if let Some(w) = self.workers.lock().unwrap().remove(&req.game.id) {
+            w.stop();
+        }
+        self.clock.end(&req.game.id);
+        self.reports.lock().unwrap().remove(&req.game.id);
         Ok(())
     }
 
+    fn last_report(&self, game_id: &str) -> Option<report::SearchReport> {
+        self.reports.lock().unwrap().get(game_id).cloned()
+    }
+
+    fn export_tree(
+        &self,
+        game_id: &str,
+        format: export::TreeFormat,
+        depth: usize,
+        width: usize,
+    ) -> Option<String> {
+        let worker = self.workers.lock().unwrap().get(game_id)?.clone();
+        let tree = worker.inspect(|root| export::export(root, depth, width))?;
+        Some(match format {
+            export::TreeFormat::Dot => export::to_dot(&tree),
+            export::TreeFormat::Json => export::to_json(&tree),
+        })
+    }
+
     fn make_move(
         &self,
         req: &crate::protocol::Request,
     ) -> Result<crate::protocol::MoveResponse, String> {
-        let game = Game::from(req);
+        let game = Game::try_from(req)?;
         let start = Instant::now();
-        let deadline = start + game.timeout - LATENCY_MARGIN;
-        let res = self.solve(game, &deadline, usize::MAX);
+        let deadline = self.clock.deadline(req, &game, start);
+        let report = if SearchMode::for_game(&game) == SearchMode::MaxN {
+            solve_max_n(&game, self.scorer.clone(), &deadline, usize::MAX)
+        } else {
+            let worker = self.worker(&req.game.id, &game);
+            let root = worker.take(&game);
+            let (report, root) = self.solve_tree(root, &deadline, usize::MAX);
+            worker.put(root, report.depth);
+            report
+        };
 
-        let (best_dir, top_score) = res
+        let (best_dir, top_score) = report
+            .best
             .map(|(dir, score)| (dir, format!("{}", score)))
             .unwrap_or((Direction::Up, "no result".to_string()));
 
-        if Instant::now() >= deadline + LATENCY_MARGIN / 2 {
-            println!(
+        self.clock.moved(&req.game.id, start);
+        if Instant::now() >= deadline + timing::DEFAULT_MARGIN / 2 {
+            log!(
                 "Warning: exceeded deadline: move took {}ms",
                 start.elapsed().as_millis()
             )
         }
+        self.reports
+            .lock()
+            .unwrap()
+            .insert(req.game.id.clone(), report);
 
         Ok(protocol::MoveResponse {
             direction: best_dir,
@@ -0,0 +1,196 @@
+use std::{
+    cmp::Reverse,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::logic::{Direction, Game, Point};
+
+// Killer moves are kept by turn, for this many turns ahead
+const KILLER_TURNS: usize = 64;
+
+/// MoveOrdering remembers the moves that caused cutoffs, so they are tried
+/// first in other positions: two killer moves per turn, and a history score
+/// per tile and direction that grows with the depth of the cutoffs. Our moves
+/// and the enemy moves are kept apart. It's shared by all threads and by the
+/// iterations of an iterative deepening search.
+pub struct MoveOrdering {
+    width: usize,
+    our_killers: Vec<[AtomicU64; 2]>,
+    their_killers: Vec<[AtomicU64; 2]>,
+    our_history: Vec<AtomicU64>,
+    their_history: Vec<AtomicU64>,
+}
+
+impl MoveOrdering {
+    pub fn new(game: &Game) -> Self {
+        let width = game.board.width() as usize;
+        let tiles = width * game.board.height() as usize;
+        let killers = || {
+            (0..KILLER_TURNS)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect()
+        };
+        let history = || (0..tiles * 4).map(|_| AtomicU64::new(0)).collect();
+        Self {
+            width,
+            our_killers: killers(),
+            their_killers: killers(),
+            our_history: history(),
+            their_history: history(),
+        }
+    }
+
+    /// clear forgets everything, for a search of an unrelated position.
+    pub fn clear(&self) {
+        for k in self.our_killers.iter().chain(self.their_killers.iter()) {
+            k[0].store(0, Ordering::Relaxed);
+            k[1].store(0, Ordering::Relaxed);
+        }
+        for h in self.our_history.iter().chain(self.their_history.iter()) {
+            h.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// order_moves sorts our moves, killers first, then by history.
+    pub fn order_moves(&self, game: &Game, moves: &mut [Direction]) {
+        let killers = &self.our_killers[game.turn % KILLER_TURNS];
+        moves.sort_by_cached_key(|dir| {
+            let history = self.history(&self.our_history, &game.you.head, *dir);
+            Reverse((killer_rank(killers, move_key(*dir)), history))
+        });
+    }
+
+    /// order_combos sorts combinations of enemy moves, killers first, then
+    /// by the history of the moves in them.
+    pub fn order_combos(&self, game: &Game, combos: &mut [Vec<Direction>]) {
+        let killers = &self.their_killers[game.turn % KILLER_TURNS];
+        combos.sort_by_cached_key(|combo| {
+            let history: u64 = game
+                .others
+                .iter()
+                .zip(combo)
+                .map(|(enemy, dir)| self.history(&self.their_history, &enemy.head, *dir))
+                .sum();
+            Reverse((killer_rank(killers, combo_key(combo)), history))
+        });
+    }
+
+    /// our_cutoff records that our move was too good for the enemies to
+    /// allow, depth is the remaining depth of the search.
+    pub fn our_cutoff(&self, game: &Game, dir: Direction, depth: usize) {
+        add_killer(&self.our_killers[game.turn % KILLER_TURNS], move_key(dir));
+        self.add_history(&self.our_history, &game.you.head, dir, depth);
+    }
+
+    /// their_cutoff records that a combination of enemy moves was too bad
+    /// for us to allow.
+    pub fn their_cutoff(&self, game: &Game, combo: &[Direction], depth: usize) {
+        add_killer(
+            &self.their_killers[game.turn % KILLER_TURNS],
+            combo_key(combo),
+        );
+        for (enemy, dir) in game.others.iter().zip(combo) {
+            self.add_history(&self.their_history, &enemy.head, *dir, depth);
+        }
+    }
+
+    #[inline(always)]
+    fn index(&self, head: &Point, dir: Direction) -> usize {
+        (head.x as usize + head.y as usize * self.width) * 4 + dir as usize
+    }
+
+    fn history(&self, table: &[AtomicU64], head: &Point, dir: Direction) -> u64 {
+        table[self.index(head, dir)].load(Ordering::Relaxed)
+    }
+
+    fn add_history(&self, table: &[AtomicU64], head: &Point, dir: Direction, depth: usize) {
+        let depth = depth as u64;
+        table[self.index(head, dir)].fetch_add(depth * depth, Ordering::Relaxed);
+    }
+}
+
+// Keys are never 0, that marks an empty killer slot
+fn move_key(dir: Direction) -> u64 {
+    dir as u64 + 1
+}
+
+fn combo_key(combo: &[Direction]) -> u64 {
+    combo.iter().fold(1, |key, dir| key << 2 | *dir as u64)
+}
+
+fn killer_rank(killers: &[AtomicU64; 2], key: u64) -> u8 {
+    if killers[0].load(Ordering::Relaxed) == key {
+        2
+    } else if killers[1].load(Ordering::Relaxed) == key {
+        1
+    } else {
+        0
+    }
+}
+
+fn add_killer(killers: &[AtomicU64; 2], key: u64) {
+    let first = killers[0].swap(key, Ordering::Relaxed);
+    if first != key {
+        killers[1].store(first, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::notation::parse_game;
+
+    #[test]
+    fn killers_and_history() {
+        let game = parse_game(
+            "
+. . . . .
+. A . B .
+. ^ . ^ .
+",
+        )
+        .unwrap();
+        let ordering = MoveOrdering::new(&game);
+        let mut moves = vec![Direction::Up, Direction::Left, Direction::Right];
+        ordering.order_moves(&game, &mut moves);
+        assert_eq!(
+            moves,
+            vec![Direction::Up, Direction::Left, Direction::Right]
+        );
+
+        // Deeper cutoffs count more, but killers go first
+        ordering.our_cutoff(&game, Direction::Right, 3);
+        ordering.our_cutoff(&game, Direction::Left, 1);
+        ordering.order_moves(&game, &mut moves);
+        assert_eq!(
+            moves,
+            vec![Direction::Left, Direction::Right, Direction::Up]
+        );
+
+        // In other turns, only the history counts
+        let mut later = game.clone();
+        later.turn += 1;
+        ordering.order_moves(&later, &mut moves);
+        assert_eq!(
+            moves,
+            vec![Direction::Right, Direction::Left, Direction::Up]
+        );
+
+        let mut combos = vec![
+            vec![Direction::Up],
+            vec![Direction::Left],
+            vec![Direction::Right],
+        ];
+        ordering.their_cutoff(&game, &[Direction::Left], 2);
+        ordering.order_combos(&game, &mut combos);
+        assert_eq!(combos[0], vec![Direction::Left]);
+
+        ordering.clear();
+        let mut moves = vec![Direction::Up, Direction::Left, Direction::Right];
+        ordering.order_moves(&game, &mut moves);
+        assert_eq!(
+            moves,
+            vec![Direction::Up, Direction::Left, Direction::Right]
+        );
+    }
+}
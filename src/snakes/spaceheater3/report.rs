@@ -0,0 +1,184 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::logic::{Direction, Game};
+
+use super::SearchMode;
+
+/// DepthReport describes a single iteration of the iterative deepening.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DepthReport {
+    pub depth: usize,
+    pub node_count: usize,
+    pub millis: u64,
+    // None if the deadline passed before the depth was completed
+    pub best: Option<(Direction, i64)>,
+}
+
+/// SearchReport describes how a move was picked, so odd moves can be
+/// explained without rebuilding with logging.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SearchReport {
+    pub turn: usize,
+    pub mode: SearchMode,
+    // The search used when the tree search thinks we're going to die
+    pub fallback: Option<SearchMode>,
+    pub best: Option<(Direction, i64)>,
+    // Deepest completed depth
+    pub depth: usize,
+    pub depths: Vec<DepthReport>,
+    pub move_scores: Vec<(Direction, Option<i64>)>,
+    // Our move and the enemy moves that answer it, for every turn
+    pub principal_variation: Vec<(Direction, Vec<Direction>)>,
+    // Part of the searched positions that alpha-beta skipped
+    pub prune_rate: f64,
+}
+
+impl SearchReport {
+    pub fn new(game: &Game, mode: SearchMode) -> Self {
+        Self {
+            turn: game.turn,
+            mode,
+            fallback: None,
+            best: None,
+            depth: 0,
+            depths: vec![],
+            move_scores: vec![],
+            principal_variation: vec![],
+            prune_rate: 0.0,
+        }
+    }
+
+    /// node_count is the number of positions scored at all depths.
+    pub fn node_count(&self) -> usize {
+        self.depths.iter().map(|d| d.node_count).sum()
+    }
+
+    pub(super) fn add_depth(&mut self, depth: DepthReport) {
+        if depth.best.is_some() {
+            self.depth = depth.depth;
+        }
+        self.depths.push(depth);
+    }
+}
+
+impl fmt::Display for SearchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "turn {}: {:?} search", self.turn, self.mode)?;
+        if let Some(fallback) = self.fallback {
+            write!(f, ", fell back to {:?}", fallback)?;
+        }
+        match self.best {
+            Some((dir, score)) => writeln!(f, ", picked {} with score {}", dir, score)?,
+            None => writeln!(f, ", no result")?,
+        }
+        writeln!(
+            f,
+            "completed depth {} after {} nodes, pruned {:.1}%",
+            self.depth,
+            self.node_count(),
+            self.prune_rate * 100.0
+        )?;
+        for d in &self.depths {
+            write!(
+                f,
+                "  depth {}: {} nodes in {}ms",
+                d.depth, d.node_count, d.millis
+            )?;
+            match d.best {
+                Some((dir, score)) => writeln!(f, ": {} {}", dir, score)?,
+                None => writeln!(f, ": aborted")?,
+            }
+        }
+        for (dir, score) in &self.move_scores {
+            match score {
+                Some(score) => writeln!(f, "  {}: {}", dir, score)?,
+                None => writeln!(f, "  {}: pruned", dir)?,
+            }
+        }
+        write!(f, "principal variation:")?;
+        for (dir, enemy_moves) in &self.principal_variation {
+            write!(f, " {}", dir)?;
+            if !enemy_moves.is_empty() {
+                let moves: Vec<_> = enemy_moves.iter().map(|d| d.to_string()).collect();
+                write!(f, " ({})", moves.join(","))?;
+            }
+        }
+        writeln!(f)
+    }
+}
+
+/// SearchStats counts the children searched and skipped by alpha-beta.
+#[derive(Default)]
+pub struct SearchStats {
+    searched: AtomicUsize,
+    pruned: AtomicUsize,
+}
+
+impl SearchStats {
+    #[inline(always)]
+    pub fn searched(&self) {
+        self.searched.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn pruned(&self) {
+        self.pruned.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn prune_rate(&self) -> f64 {
+        let searched = self.searched.load(Ordering::Relaxed);
+        let pruned = self.pruned.load(Ordering::Relaxed);
+        if searched + pruned == 0 {
+            0.0
+        } else {
+            pruned as f64 / (searched + pruned) as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        logic::{notation::parse_game, scoring},
+        snakes::Spaceheater3,
+    };
+
+    use super::*;
+
+    #[test]
+    fn report_search() {
+        let game = parse_game(
+            "
+. . . . . . . . . . .
+. A . . . . . . . B .
+. ^ . . . . . . . ^ .
+. . . . . . . . . . .
+. . . . . . . . . . .
+",
+        )
+        .unwrap();
+        let snake = Spaceheater3::new(scoring::tournament_score, None);
+        let deadline = Instant::now() + Duration::from_secs(600);
+        let report = snake.solve(game, &deadline, 5);
+
+        assert_eq!(report.mode, SearchMode::Paranoid);
+        assert_eq!(report.fallback, None);
+        assert_eq!(report.depth, report.depths.last().unwrap().depth);
+        assert_eq!(report.best, report.depths.last().unwrap().best);
+        let (best_move, best_score) = report.best.unwrap();
+        assert!(report.move_scores.contains(&(best_move, Some(best_score))));
+        assert_eq!(report.principal_variation[0].0, best_move);
+        assert_eq!(report.principal_variation[0].1.len(), 1);
+        assert!(report.prune_rate > 0.0 && report.prune_rate < 1.0);
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<SearchReport>(&json).unwrap(), report);
+    }
+}
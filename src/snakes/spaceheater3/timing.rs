@@ -0,0 +1,262 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{log, logic::Game, protocol::Request};
+
+use super::util::{certain_death, quiet};
+
+/// DEFAULT_MARGIN is the time kept for the network until the latency of a
+/// game is known.
+pub const DEFAULT_MARGIN: Duration = Duration::from_millis(115);
+// Never cut it closer than this, whatever the network did so far
+const MIN_MARGIN: Duration = Duration::from_millis(20);
+// Part of the margin kept in critical positions, the rest is spent thinking
+const CRITICAL_MARGIN: f64 = 0.75;
+// Latency samples needed before the default margin is replaced
+const MIN_SAMPLES: usize = 3;
+// Weight of a new latency sample in the averages
+const SAMPLE_WEIGHT: f64 = 0.2;
+// A move that came back within this much of the timeout was nearly too late
+const NEAR_TIMEOUT: Duration = Duration::from_millis(25);
+// Budget used after a near-timeout, and how fast it recovers
+const SHRINK: f64 = 0.75;
+const MIN_SHRINK: f64 = 0.5;
+const RECOVERY: f64 = 0.05;
+
+/// Urgency is how much the move for a position is worth thinking about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Urgency {
+    // At most one move that isn't certain death
+    Trivial,
+    Normal,
+    // An enemy head nearby, or only two safe moves
+    Critical,
+}
+
+impl Urgency {
+    pub fn of(game: &Game) -> Self {
+        let safe_moves = game
+            .you
+            .head
+            .neighbours()
+            .into_iter()
+            .filter(|(_, p)| {
+                let mut p = *p;
+                game.warp(&mut p);
+                !certain_death(game, &game.you, &p)
+            })
+            .count();
+        if safe_moves <= 1 {
+            Urgency::Trivial
+        } else if safe_moves == 2 || !quiet(game) {
+            Urgency::Critical
+        } else {
+            Urgency::Normal
+        }
+    }
+
+    // Part of the time budget to use
+    fn share(&self) -> f64 {
+        match self {
+            Urgency::Trivial => 0.25,
+            Urgency::Normal | Urgency::Critical => 1.0,
+        }
+    }
+}
+
+// Clock keeps the timing of a single game.
+#[derive(Clone, Debug)]
+struct Clock {
+    // Latency on top of our own thinking time, in ms
+    mean: f64,
+    variance: f64,
+    samples: usize,
+    // How long we took for the last move
+    last_move: Option<Duration>,
+    // Part of the budget we use, lower after near-timeouts
+    shrink: f64,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            mean: 0.0,
+            variance: 0.0,
+            samples: 0,
+            last_move: None,
+            shrink: 1.0,
+        }
+    }
+}
+
+impl Clock {
+    // observe learns from the latency the engine reported for our last move
+    fn observe(&mut self, latency: Duration, timeout: Duration) {
+        let last_move = match self.last_move.take() {
+            Some(d) => d,
+            None => return,
+        };
+        if latency.is_zero() {
+            return;
+        }
+
+        if latency + NEAR_TIMEOUT >= timeout {
+            log!(
+                "Warning: last move came back after {}ms, shrinking the time budget",
+                latency.as_millis()
+            );
+            self.shrink = (self.shrink * SHRINK).max(MIN_SHRINK);
+        } else {
+            self.shrink = (self.shrink + RECOVERY).min(1.0);
+        }
+
+        let sample = latency.saturating_sub(last_move).as_secs_f64() * 1000.0;
+        if self.samples == 0 {
+            self.mean = sample;
+        } else {
+            let diff = sample - self.mean;
+            self.mean += SAMPLE_WEIGHT * diff;
+            self.variance = (1.0 - SAMPLE_WEIGHT) * (self.variance + SAMPLE_WEIGHT * diff * diff);
+        }
+        self.samples += 1;
+    }
+
+    // margin is the time to keep for the network
+    fn margin(&self, timeout: Duration) -> Duration {
+        let margin = if self.samples < MIN_SAMPLES {
+            DEFAULT_MARGIN
+        } else {
+            let ms = self.mean + 3.0 * self.variance.sqrt();
+            MIN_MARGIN + Duration::from_secs_f64(ms.max(0.0) / 1000.0)
+        };
+        margin.min(timeout / 2)
+    }
+
+    fn budget(&self, timeout: Duration, urgency: Urgency) -> Duration {
+        let mut margin = self.margin(timeout);
+        if urgency == Urgency::Critical {
+            margin = margin.mul_f64(CRITICAL_MARGIN).max(MIN_MARGIN);
+        }
+        let available = timeout.saturating_sub(margin);
+        available.mul_f64(self.shrink * urgency.share())
+    }
+}
+
+/// TimeManager decides how long to think about a move. It learns the
+/// network latency of every game from the latency the engine reports for
+/// our previous move, spends part of the network margin on critical
+/// positions and little time on trivial ones, and uses less time after a
+/// move nearly timed out.
+#[derive(Default)]
+pub struct TimeManager {
+    games: Mutex<HashMap<String, Clock>>,
+}
+
+impl TimeManager {
+    /// deadline returns when the search for the move requested by req has to
+    /// stop, start is when the request came in.
+    pub fn deadline(&self, req: &Request, game: &Game, start: Instant) -> Instant {
+        let mut games = self.games.lock().unwrap();
+        let clock = games.entry(req.game.id.clone()).or_default();
+        if let Ok(ms) = req.you.latency.parse::<u64>() {
+            clock.observe(Duration::from_millis(ms), game.timeout);
+        }
+        start + clock.budget(game.timeout, Urgency::of(game))
+    }
+
+    /// moved records how long we took for a move, when the response is ready.
+    pub fn moved(&self, game_id: &str, start: Instant) {
+        if let Some(clock) = self.games.lock().unwrap().get_mut(game_id) {
+            clock.last_move = Some(start.elapsed());
+        }
+    }
+
+    /// end forgets a game.
+    pub fn end(&self, game_id: &str) {
+        self.games.lock().unwrap().remove(game_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::notation::parse_game;
+
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    fn play(clock: &mut Clock, thinking: u64, latency: u64) {
+        clock.last_move = Some(Duration::from_millis(thinking));
+        clock.observe(Duration::from_millis(latency), TIMEOUT);
+    }
+
+    #[test]
+    fn learn_latency() {
+        let mut clock = Clock::default();
+        assert_eq!(clock.margin(TIMEOUT), DEFAULT_MARGIN);
+        for _ in 0..10 {
+            play(&mut clock, 400, 440);
+        }
+        assert_eq!(clock.margin(TIMEOUT), Duration::from_millis(60));
+
+        // A jittery network needs a larger margin
+        let mut jittery = clock.clone();
+        for i in 0..10 {
+            play(&mut jittery, 400, 420 + 40 * (i % 2));
+        }
+        assert!(jittery.margin(TIMEOUT) > clock.margin(TIMEOUT));
+
+        let normal = clock.budget(TIMEOUT, Urgency::Normal);
+        play(&mut clock, 400, 490);
+        assert!(clock.budget(TIMEOUT, Urgency::Normal) < normal.mul_f64(SHRINK + 0.01));
+        for _ in 0..20 {
+            play(&mut clock, 400, 440);
+        }
+        assert!(clock.budget(TIMEOUT, Urgency::Normal) > normal.mul_f64(0.95));
+    }
+
+    #[test]
+    fn urgency() {
+        let game = |text| parse_game(text).unwrap();
+        let corridor = game(
+            "
+A: length=3
+# . #
+# A #
+# ^ #
+",
+        );
+        let open = game(
+            "
+. . . . . . .
+. A . . . . .
+. ^ . . . B .
+. . . . . ^ .
+",
+        );
+        let close = game(
+            "
+. . . . . . .
+. A . B . . .
+. ^ . ^ . . .
+. . . . . . .
+",
+        );
+        assert_eq!(Urgency::of(&corridor), Urgency::Trivial);
+        assert_eq!(Urgency::of(&open), Urgency::Normal);
+        assert_eq!(Urgency::of(&close), Urgency::Critical);
+
+        let clock = Clock::default();
+        assert!(clock.budget(TIMEOUT, Urgency::Trivial) < clock.budget(TIMEOUT, Urgency::Normal));
+        assert_eq!(
+            clock.budget(TIMEOUT, Urgency::Normal),
+            TIMEOUT - DEFAULT_MARGIN
+        );
+        assert_eq!(
+            clock.budget(TIMEOUT, Urgency::Critical),
+            TIMEOUT - DEFAULT_MARGIN.mul_f64(CRITICAL_MARGIN)
+        );
+    }
+}
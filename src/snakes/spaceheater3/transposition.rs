@@ -0,0 +1,339 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{logic::Direction, protocol::ALL_DIRECTIONS};
+
+/// DEFAULT_SIZE is the number of entries in a transposition table, 24 MB.
+pub const DEFAULT_SIZE: usize = 1 << 20;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Bound {
+    // The score is the score of the position
+    Exact,
+    // The search was cut off, the position scores at least this much
+    Lower,
+    // The search was cut off, the position scores at most this much
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Entry {
+    pub bound: Bound,
+    // Remaining search depth the score was calculated for
+    pub depth: u8,
+    pub score: i64,
+    pub best_move: Direction,
+    pub will_die: bool,
+}
+
+impl Entry {
+    /// cutoff returns the score if it can be used instead of searching to
+    /// the given depth with the given alpha and beta.
+    pub fn cutoff(&self, depth: usize, alpha: i64, beta: i64) -> Option<i64> {
+        if (self.depth as usize) < depth {
+            return None;
+        }
+        match self.bound {
+            Bound::Exact => Some(self.score),
+            Bound::Lower if self.score >= beta => Some(self.score),
+            Bound::Upper if self.score <= alpha => Some(self.score),
+            _ => None,
+        }
+    }
+}
+
+// Entries are three words: the key xor'ed with the other two, the score and
+// the packed bound, depth, move and generation. Threads write without
+// locking, a torn write makes the key check fail and reads as a miss.
+struct Slot {
+    check: AtomicU64,
+    score: AtomicU64,
+    data: AtomicU64,
+}
+
+/// TranspositionTable stores search results by position hash, so positions
+/// reached through different move orders or in an earlier iteration of the
+/// search don't have to be searched again. It can be shared by all threads.
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    generation: AtomicU64,
+}
+
+impl TranspositionTable {
+    /// new creates a table with the given number of entries, rounded up to
+    /// a power of two.
+    pub fn new(size: usize) -> Self {
+        let slots = (0..size.next_power_of_two())
+            .map(|_| Slot {
+                check: AtomicU64::new(0),
+                score: AtomicU64::new(0),
+                data: AtomicU64::new(0),
+            })
+            .collect();
+        Self {
+            slots,
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// new_search ages the current entries, so they are replaced first.
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    fn slot(&self, key: u64) -> &Slot {
+        &self.slots[key as usize & (self.slots.len() - 1)]
+    }
+
+    pub fn get(&self, key: u64) -> Option<Entry> {
+        let slot = self.slot(key);
+        let score = slot.score.load(Ordering::Relaxed);
+        let data = slot.data.load(Ordering::Relaxed);
+        if data == 0 || slot.check.load(Ordering::Relaxed) != key ^ score ^ data {
+            return None;
+        }
+        Some(unpack(score, data))
+    }
+
+    /// put stores an entry, unless the slot holds a deeper search of another
+    /// position from the current search.
+    pub fn put(&self, key: u64, entry: Entry) {
+        let slot = self.slot(key);
+        let generation = self.generation.load(Ordering::Relaxed) & 0xffff;
+        let old = slot.data.load(Ordering::Relaxed);
+        if old != 0
+            && (old >> 16) & 0xffff == generation
+            && (old >> 8) as u8 > entry.depth
+            && slot.check.load(Ordering::Relaxed) ^ slot.score.load(Ordering::Relaxed) ^ old != key
+        {
+            return;
+        }
+
+        let score = entry.score as u64;
+        let data = pack(&entry, generation);
+        slot.check.store(key ^ score ^ data, Ordering::Relaxed);
+        slot.score.store(score, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIZE)
+    }
+}
+
+// bit 0: in use, 1-2: bound, 3-4: best move, 5: will die, 8-15: depth,
+// 16-31: generation
+fn pack(entry: &Entry, generation: u64) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+    let best_move = ALL_DIRECTIONS
+        .iter()
+        .position(|d| *d == entry.best_move)
+        .unwrap() as u64;
+    1 | bound << 1
+        | best_move << 3
+        | (entry.will_die as u64) << 5
+        | (entry.depth as u64) << 8
+        | generation << 16
+}
+
+fn unpack(score: u64, data: u64) -> Entry {
+    Entry {
+        bound: match (data >> 1) & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        },
+        depth: (data >> 8) as u8,
+        score: score as i64,
+        best_move: ALL_DIRECTIONS[(data >> 3) as usize & 0b11],
+        will_die: data & 1 << 5 != 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{
+        logic::{scoring, Game},
+        protocol,
+        snakes::spaceheater3::{
+            alphabeta::AlphaBeta, max::MaximizingNode, ordering::MoveOrdering, SearchContext,
+            SearchMode,
+        },
+    };
+
+    fn entry(depth: u8, score: i64) -> Entry {
+        Entry {
+            bound: Bound::Lower,
+            depth,
+            score,
+            best_move: Direction::Left,
+            will_die: true,
+        }
+    }
+
+    #[test]
+    fn store_and_replace() {
+        let tt = TranspositionTable::new(16);
+        assert_eq!(tt.get(42), None);
+        tt.put(42, entry(3, -17));
+        assert_eq!(tt.get(42), Some(entry(3, -17)));
+        // Same slot, different position
+        assert_eq!(tt.get(42 + 16), None);
+
+        // Shallower results for other positions don't replace deeper ones
+        tt.put(42 + 16, entry(2, 5));
+        assert_eq!(tt.get(42 + 16), None);
+        assert_eq!(tt.get(42), Some(entry(3, -17)));
+        tt.put(42, entry(2, 5));
+        assert_eq!(tt.get(42), Some(entry(2, 5)));
+
+        // Until the next search
+        tt.put(42, entry(3, -17));
+        tt.new_search();
+        tt.put(42 + 16, entry(1, 5));
+        assert_eq!(tt.get(42 + 16), Some(entry(1, 5)));
+        assert_eq!(tt.get(42), None);
+    }
+
+    #[test]
+    fn cutoffs() {
+        let e = entry(3, 10);
+        assert_eq!(e.cutoff(4, 0, 5), None);
+        assert_eq!(e.cutoff(3, 0, 5), Some(10));
+        assert_eq!(e.cutoff(3, 0, 20), None);
+        let e = Entry {
+            bound: Bound::Upper,
+            ..e
+        };
+        assert_eq!(e.cutoff(2, 10, 20), Some(10));
+        assert_eq!(e.cutoff(2, 0, 20), None);
+        let e = Entry {
+            bound: Bound::Exact,
+            ..e
+        };
+        assert_eq!(e.cutoff(2, 0, 5), Some(10));
+    }
+
+    #[test]
+    fn same_result_as_search_without_table() {
+        let request: protocol::Request = serde_json::from_str(include_str!(
+            "../../logic/tests/data/wrapped_rivers_and_lakes_opening.json"
+        ))
+        .unwrap();
+        let game = Game::try_from(&request).unwrap();
+        let search =
+            |tt: &TranspositionTable, depths: std::ops::RangeInclusive<usize>, threads: f32| {
+                let ordering = MoveOrdering::new(&game);
+                let ctx = SearchContext {
+                    deadline: Instant::now() + Duration::from_secs(600),
+                    mode: SearchMode::Paranoid,
+                    scorer: &scoring::tournament_score,
+                    tt,
+                    ordering: &ordering,
+                    interrupt: None,
+                    stats: &Default::default(),
+                    max_turn: 0,
+                };
+                let mut root = MaximizingNode::new(game.clone());
+                let mut res = (None, 0);
+                for depth in depths {
+                    res = root.solve(&ctx, depth, &AlphaBeta::new(i64::MIN, i64::MAX), threads);
+                }
+                res
+            };
+
+        // A single slot is overwritten all the time, and almost never hits
+        let (expected, full_node_count) = search(&TranspositionTable::new(1), 2..=2, 1.0);
+        let (score, node_count) = search(&TranspositionTable::new(1 << 16), 1..=2, 1.0);
+        assert!(expected.is_some());
+        assert_eq!(score, expected);
+        assert!(node_count < full_node_count);
+
+        let (score, _) = search(&TranspositionTable::new(1 << 16), 1..=2, 4.0);
+        assert_eq!(score.map(|s| s.1), expected.map(|s| s.1));
+    }
+
+    #[test]
+    fn kills_are_part_of_the_key() {
+        let request: protocol::Request = serde_json::from_str(include_str!(
+            "../../logic/tests/data/wrapped_rivers_and_lakes_opening.json"
+        ))
+        .unwrap();
+        let game = Game::try_from(&request).unwrap();
+        let tt = TranspositionTable::new(1 << 16);
+        let ctx = SearchContext {
+            deadline: Instant::now() + Duration::from_secs(600),
+            mode: SearchMode::Paranoid,
+            scorer: &scoring::tournament_score,
+            tt: &tt,
+            ordering: &MoveOrdering::new(&game),
+            interrupt: None,
+            stats: &Default::default(),
+            max_turn: 0,
+        };
+        let mut root = MaximizingNode::new(game);
+        root.solve(&ctx, 2, &AlphaBeta::new(i64::MIN, i64::MAX), 1.0);
+
+        let position = &root.children[0].children[0].game;
+        assert!(tt.get(position.exact_zobrist()).is_some());
+        // The same board, reached from a root where a snake died since then,
+        // scores the kill
+        let mut later = position.clone();
+        later.dead_snakes += 1;
+        assert_eq!(tt.get(later.exact_zobrist()), None);
+    }
+
+    #[test]
+    fn windows_closed_by_siblings() {
+        fn slow_score(game: &Game) -> i64 {
+            std::thread::sleep(Duration::from_millis(2));
+            scoring::tournament_score(game)
+        }
+
+        let request: protocol::Request = serde_json::from_str(include_str!(
+            "../../logic/tests/data/wrapped_rivers_and_lakes_opening.json"
+        ))
+        .unwrap();
+        let game = Game::try_from(&request).unwrap();
+        let tt = TranspositionTable::new(1 << 12);
+        let ordering = MoveOrdering::new(&game);
+        let ctx = SearchContext {
+            deadline: Instant::now() + Duration::from_secs(600),
+            mode: SearchMode::Paranoid,
+            scorer: &slow_score,
+            tt: &tt,
+            ordering: &ordering,
+            interrupt: None,
+            stats: &Default::default(),
+            max_turn: 0,
+        };
+
+        // A sibling of an ancestor closes the window while the node is
+        // searched, the children after that are skipped
+        let ancestor = AlphaBeta::new(i64::MIN, i64::MAX);
+        let parent = ancestor.new_child();
+        let mut node = MaximizingNode::new(game.clone());
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(10));
+                ancestor.new_alpha_score(i64::MAX - 1);
+                ancestor.new_beta_score(i64::MAX - 1);
+            });
+            node.solve(&ctx, 1, &parent, 1.0);
+        });
+
+        // The score of the children that were searched says nothing about
+        // the ones that were skipped
+        let entry = tt.get(game.exact_zobrist());
+        assert_ne!(entry.map(|e| e.bound), Some(Bound::Upper));
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
 pub fn certain_death(game: &Game, snake: &Snake, p: &Point) -> bool {
     match game.board.get(p) {
         Tile::Hazard(x) | Tile::HazardWithHead(x) => {
-            game.rules.hazard_damage_per_turn * x as i8 > snake.health
+            game.rules.hazard_damage_per_turn.saturating_mul(x as i8) > snake.health
         }
         Tile::HazardWithSnake(x) => {
             let mut self_collision = false;
@@ -19,7 +19,7 @@ pub fn certain_death(game: &Game, snake: &Snake, p: &Point) -> bool {
             if self_collision {
                 true
             } else {
-                game.rules.hazard_damage_per_turn * x as i8 > snake.health
+                game.rules.hazard_damage_per_turn.saturating_mul(x as i8) > snake.health
             }
         }
         Tile::Snake => {
@@ -41,6 +41,46 @@ pub fn certain_death(game: &Game, snake: &Snake, p: &Point) -> bool {
     }
 }
 
+/// lost is true once we're out of the game. Searches don't look past a lost
+/// position, its score is final.
+pub fn lost(game: &Game) -> bool {
+    game.you.dead()
+}
+
+/// sensible_moves lists the moves of a snake that aren't certain death. If
+/// every move is, the snake is lost, but which way it goes still decides
+/// whether a head-to-head takes us or an enemy with it. Then it lists all
+/// moves for us and for enemies that can meet our head, and a single one for
+/// the others.
+pub fn sensible_moves(game: &Game, snake: &Snake) -> Vec<Direction> {
+    let moves: Vec<Direction> = ALL_DIRECTIONS
+        .into_iter()
+        .filter(|d| {
+            let mut p = snake.head.neighbour(*d);
+            game.warp(&mut p);
+            !certain_death(game, snake, &p)
+        })
+        .collect();
+    if !moves.is_empty() {
+        moves
+    } else if snake.id == game.you.id || meets_our_head(game, snake) {
+        ALL_DIRECTIONS.to_vec()
+    } else {
+        vec![Direction::Up]
+    }
+}
+
+// True if the snake can move to a tile our head can move to
+fn meets_our_head(game: &Game, snake: &Snake) -> bool {
+    snake.head.neighbours().into_iter().any(|(_, mut p)| {
+        game.warp(&mut p);
+        game.you.head.neighbours().into_iter().any(|(_, mut n)| {
+            game.warp(&mut n);
+            n == p
+        })
+    })
+}
+
 pub fn all_sensible_enemy_moves(game: &Game) -> Vec<Vec<Direction>> {
     if game.others.len() == 0 {
         return vec![vec![]];
@@ -49,20 +89,20 @@ pub fn all_sensible_enemy_moves(game: &Game) -> Vec<Vec<Direction>> {
     let mut all_enemy_moves: Vec<Vec<Direction>> = vec![];
 
     for enemy in &game.others {
-        let enemy_moves: Vec<Direction> = ALL_DIRECTIONS
-            .into_iter()
-            .filter(|d| {
-                let mut p = enemy.head.neighbour(*d);
-                game.warp(&mut p);
-                !certain_death(game, enemy, &p)
-            })
-            .collect();
+        if enemy.same_squad(&game.you) {
+            // Teammates are on our side, they don't get to pick the move that hurts us most
+            let ally_move = ally_move(game, enemy);
+            if all_enemy_moves.is_empty() {
+                all_enemy_moves = vec![vec![ally_move]];
+            } else {
+                for combo in all_enemy_moves.iter_mut() {
+                    combo.push(ally_move);
+                }
+            }
+            continue;
+        }
 
-        let enemy_moves = if enemy_moves.len() == 0 {
-            vec![Direction::Up]
-        } else {
-            enemy_moves
-        };
+        let enemy_moves = sensible_moves(game, enemy);
         if all_enemy_moves.is_empty() {
             all_enemy_moves = enemy_moves.into_iter().map(|d| vec![d]).collect();
         } else {
@@ -80,3 +120,173 @@ pub fn all_sensible_enemy_moves(game: &Game) -> Vec<Vec<Direction>> {
 
     all_enemy_moves
 }
+
+/// quiet is false when the next move decides something the scorer can't
+/// see yet: an enemy head can reach a tile next to ours, or we have at most
+/// one move that isn't certain death.
+pub fn quiet(game: &Game) -> bool {
+    let mut safe_moves = 0;
+    for (_, mut p) in game.you.head.neighbours() {
+        game.warp(&mut p);
+        if !certain_death(game, &game.you, &p) {
+            safe_moves += 1;
+        }
+        for enemy in &game.others {
+            if enemy.same_squad(&game.you) {
+                continue;
+            }
+            if p == enemy.head {
+                return false;
+            }
+            for (_, mut n) in p.neighbours() {
+                game.warp(&mut n);
+                if n == enemy.head {
+                    return false;
+                }
+            }
+        }
+    }
+    safe_moves > 1
+}
+
+/// best_reply_enemy_moves lists the enemy moves for Best-Reply Search:
+/// every enemy plays its default move, except for a single enemy that can
+/// pick any sensible move. That's one combination per enemy move instead of
+/// one for every combination of them.
+pub fn best_reply_enemy_moves(game: &Game) -> Vec<Vec<Direction>> {
+    let defaults: Vec<Direction> = game
+        .others
+        .iter()
+        .map(|enemy| {
+            if enemy.same_squad(&game.you) {
+                ally_move(game, enemy)
+            } else {
+                default_move(game, enemy)
+            }
+        })
+        .collect();
+
+    let mut all_enemy_moves = vec![defaults.clone()];
+    for (i, enemy) in game.others.iter().enumerate() {
+        if enemy.same_squad(&game.you) {
+            continue;
+        }
+        for enemy_move in sensible_moves(game, enemy) {
+            if enemy_move != defaults[i] {
+                let mut combo = defaults.clone();
+                combo[i] = enemy_move;
+                all_enemy_moves.push(combo);
+            }
+        }
+    }
+    all_enemy_moves
+}
+
+/// default_move is the move an enemy is assumed to make when it's not the
+/// one going after us: the sensible move with the most free tiles around it.
+fn default_move(game: &Game, enemy: &Snake) -> Direction {
+    let room = |dir: &Direction| {
+        let mut p = enemy.head.neighbour(*dir);
+        game.warp(&mut p);
+        p.neighbours()
+            .into_iter()
+            .filter(|(_, n)| {
+                let mut n = *n;
+                game.warp(&mut n);
+                game.board.get(&n).is_safe()
+            })
+            .count()
+    };
+    let moves = sensible_moves(game, enemy);
+    // max_by_key returns the last maximum, keep the order of ALL_DIRECTIONS
+    moves
+        .iter()
+        .rev()
+        .max_by_key(|dir| room(dir))
+        .copied()
+        .unwrap()
+}
+
+/// ally_move picks a single move for a teammate: a move that doesn't kill
+/// it, preferably one that stays clear of our own head.
+fn ally_move(game: &Game, ally: &Snake) -> Direction {
+    let mut best = None;
+    for (dir, mut p) in ally.head.neighbours() {
+        game.warp(&mut p);
+        if certain_death(game, ally, &p) {
+            continue;
+        }
+        let mut next_to_us = false;
+        for (_, mut n) in game.you.head.neighbours() {
+            game.warp(&mut n);
+            next_to_us = next_to_us || n == p;
+        }
+        if !next_to_us {
+            return dir;
+        }
+        best = best.or(Some(dir));
+    }
+    best.unwrap_or(Direction::Up)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::notation::parse_game;
+
+    #[test]
+    fn best_reply_moves() {
+        let game = parse_game(
+            "
+. . . . . . . . .
+. A . . B . . C .
+. ^ . . ^ . . ^ .
+. . . . . . . . .
+. D . . E . . F .
+. ^ . . ^ . . ^ .
+. . . . . . . . .
+. G . . H . . . .
+. ^ . . ^ . . . .
+",
+        )
+        .unwrap();
+        let all = all_sensible_enemy_moves(&game);
+        let best_reply = best_reply_enemy_moves(&game);
+        // Snakes of length 2 can move onto their own tail
+        assert_eq!(all.len(), 4usize.pow(7));
+        // The default moves, and three other moves for every enemy
+        assert_eq!(best_reply.len(), 1 + 7 * 3);
+
+        let defaults = &best_reply[0];
+        for combo in &best_reply[1..] {
+            assert!(all.contains(combo));
+            let changed = combo.iter().zip(defaults).filter(|(a, b)| a != b).count();
+            assert_eq!(changed, 1);
+        }
+        // The tail of E is right in front of H, there's more room to the left
+        assert_eq!(defaults[6], Direction::Left);
+    }
+
+    #[test]
+    fn doomed_enemies() {
+        // B and C have nowhere to go, but C can still meet us head to head
+        let game = parse_game(
+            "
+hazard_damage: 14
+C: health=10
+# B # . # A .! C #
+# ^ # . # ^ # ^ #
+# ^ # . # ^ # ^ #
+. . . . . . . . .
+",
+        )
+        .unwrap();
+        assert_eq!(sensible_moves(&game, &game.others[0]), vec![Direction::Up]);
+        assert_eq!(
+            sensible_moves(&game, &game.others[1]),
+            ALL_DIRECTIONS.to_vec()
+        );
+        assert_eq!(sensible_moves(&game, &game.you), vec![Direction::Right]);
+        assert_eq!(all_sensible_enemy_moves(&game).len(), 4);
+    }
+}
@@ -0,0 +1,350 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    log,
+    logic::{self, Game, Snake},
+};
+
+use super::{
+    max::MaximizingNode, ordering::MoveOrdering, transposition::TranspositionTable, SearchContext,
+    SearchMode,
+};
+
+/// IDLE_TIMEOUT is how long a worker keeps searching after the last request
+/// for its game.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+// Deeper searches never finish in the time between two moves
+const MAX_DEPTH: usize = 32;
+
+struct State {
+    // None while a move request is searching the tree
+    root: Option<MaximizingNode>,
+    // Deepest search of the root completed in the background
+    depth: usize,
+    // True while the background search has the tree
+    searching: bool,
+    last_request: Instant,
+    shutdown: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+    // Stops the background search, so a request can take the tree
+    interrupt: AtomicBool,
+}
+
+/// Worker keeps searching the tree of a game in a background thread while
+/// the other snakes are thinking. Move requests take the subtree for the
+/// current position from it and hand it back when they're done.
+pub struct Worker {
+    shared: Arc<Shared>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Worker {
+    pub fn start<S>(game: Game, scorer: S, tt: Arc<TranspositionTable>, extensions: usize) -> Self
+    where
+        S: logic::scoring::Scorer + Send + Sync + Clone + 'static,
+    {
+        let ordering = MoveOrdering::new(&game);
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                root: Some(MaximizingNode::new(game)),
+                depth: 0,
+                searching: false,
+                last_request: Instant::now(),
+                shutdown: false,
+            }),
+            changed: Condvar::new(),
+            interrupt: AtomicBool::new(false),
+        });
+        let worker_shared = shared.clone();
+        let handle = thread::spawn(move || run(worker_shared, scorer, tt, ordering, extensions));
+        Self {
+            shared,
+            handle: Mutex::new(Some(handle)),
+        }
+    }
+
+    /// take returns the tree to search for a position: the subtree of the
+    /// tree searched so far that matches it, or a new tree if there is none.
+    pub fn take(&self, game: &Game) -> MaximizingNode {
+        let mut state = self.interrupt();
+        state.last_request = Instant::now();
+        let root = state.root.take();
+        self.shared.interrupt.store(false, Ordering::Relaxed);
+        drop(state);
+
+        if root.is_none() {
+            log!(
+                "turn {}: the search tree was lost, starting a new one",
+                game.turn
+            );
+        }
+        let mut root = root
+            .and_then(|root| subtree(root, game))
+            .unwrap_or_else(|| MaximizingNode::new(game.clone()));
+        root.game = game.clone();
+        root
+    }
+
+    /// inspect calls f with the tree searched so far, if the worker has one.
+    pub fn inspect<T>(&self, f: impl FnOnce(&MaximizingNode) -> T) -> Option<T> {
+        let state = self.interrupt();
+        let res = state.root.as_ref().map(f);
+        self.shared.interrupt.store(false, Ordering::Relaxed);
+        res
+    }
+
+    /// put hands the tree back after a move request, with the depth the
+    /// request completed. The worker continues searching it one depth deeper
+    /// until the next request.
+    pub fn put(&self, root: MaximizingNode, depth: usize) {
+        let mut state = self.lock();
+        state.root = Some(root);
+        state.depth = depth;
+        state.last_request = Instant::now();
+        self.shared.changed.notify_all();
+    }
+
+    /// stop ends the background search and waits for the thread to exit.
+    pub fn stop(&self) {
+        self.lock().shutdown = true;
+        self.shared.interrupt.store(true, Ordering::Relaxed);
+        self.shared.changed.notify_all();
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+    }
+
+    /// finished is true once the worker stopped, because the game ended or
+    /// no requests came in for a while.
+    pub fn finished(&self) -> bool {
+        self.handle
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_none_or(|h| h.is_finished())
+    }
+
+    // Stops the background search, and waits for the tree to come back. The
+    // search checks the interrupt at every node, so that doesn't take long.
+    fn interrupt(&self) -> MutexGuard<'_, State> {
+        self.shared.interrupt.store(true, Ordering::Relaxed);
+        let mut state = self.lock();
+        // The thread only exits without handing the tree back if it panicked
+        while state.searching && Arc::strong_count(&self.shared) > 1 {
+            state = self
+                .shared
+                .changed
+                .wait_timeout(state, Duration::from_millis(5))
+                .unwrap()
+                .0;
+        }
+        state
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.shared.state.lock().unwrap()
+    }
+}
+
+fn run<S>(
+    shared: Arc<Shared>,
+    scorer: S,
+    tt: Arc<TranspositionTable>,
+    ordering: MoveOrdering,
+    extensions: usize,
+) where
+    S: logic::scoring::Scorer + Send + Sync + Clone + 'static,
+{
+    lower_priority();
+    let mut ordered_turn = None;
+    loop {
+        let (mut root, depth, deadline) = {
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                if state.shutdown || state.last_request.elapsed() > IDLE_TIMEOUT {
+                    return;
+                }
+                let searchable = state
+                    .root
+                    .as_ref()
+                    .is_some_and(|r| !r.will_die && state.depth < MAX_DEPTH);
+                if searchable && !shared.interrupt.load(Ordering::Relaxed) {
+                    break;
+                }
+                state = shared
+                    .changed
+                    .wait_timeout(state, Duration::from_millis(100))
+                    .unwrap()
+                    .0;
+            }
+            state.searching = true;
+            (
+                state.root.take().unwrap(),
+                state.depth,
+                state.last_request + IDLE_TIMEOUT,
+            )
+        };
+
+        if ordered_turn != Some(root.game.turn) {
+            // A new position, the moves that were good in the last one aren't
+            ordering.clear();
+            ordered_turn = Some(root.game.turn);
+        }
+        let ctx = SearchContext {
+            deadline,
+            mode: SearchMode::for_game(&root.game),
+            scorer: &scorer,
+            tt: &tt,
+            ordering: &ordering,
+            interrupt: Some(&shared.interrupt),
+            stats: &Default::default(),
+            max_turn: root.game.turn + depth + 1 + extensions,
+        };
+        // A single thread, the move requests get the others
+        let guess = root.score.filter(|_| depth > 0).map(|s| s.1);
+        let (res, _) = root.solve_aspiration(&ctx, depth + 1, guess, 1.0);
+
+        let mut state = shared.state.lock().unwrap();
+        state.root = Some(root);
+        state.searching = false;
+        if res.is_some() {
+            state.depth = depth + 1;
+        }
+        shared.changed.notify_all();
+    }
+}
+
+/// subtree finds the node for a position in a tree: the root itself, or a
+/// node one move deeper. The search doesn't know where food will spawn, so
+/// after food spawned the position won't be in the tree.
+fn subtree(root: MaximizingNode, game: &Game) -> Option<MaximizingNode> {
+    if same_position(&root.game, game) {
+        return Some(root);
+    }
+    root.children
+        .into_iter()
+        .flat_map(|min_node| min_node.children)
+        .find(|max_node| same_position(&max_node.game, game))
+}
+
+// Snake numbers change when snakes die, so the snakes are compared by body
+fn same_position(a: &Game, b: &Game) -> bool {
+    let same_snake = |s1: &Snake, s2: &Snake| {
+        s1.body == s2.body && s1.health == s2.health && s1.length == s2.length
+    };
+    a.turn == b.turn
+        && a.board == b.board
+        && same_snake(&a.you, &b.you)
+        && a.others.len() == b.others.len()
+        && a.others
+            .iter()
+            .all(|s1| b.others.iter().any(|s2| same_snake(s1, s2)))
+}
+
+// Background searches only get the CPU time move requests don't use
+#[cfg(target_os = "linux")]
+fn lower_priority() {
+    // On Linux, this only changes the priority of the current thread
+    unsafe {
+        libc::setpriority(libc::PRIO_PROCESS, 0, 19);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lower_priority() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::{notation::parse_game, scoring, Direction};
+    use crate::snakes::spaceheater3::alphabeta::AlphaBeta;
+
+    const POSITION: &str = "
+. . . . . . .
+. . . . . . .
+. A . . . B .
+. ^ . . . ^ .
+. . . . . . .
+";
+
+    #[test]
+    fn find_subtree() {
+        let game = parse_game(POSITION).unwrap();
+        let tt = TranspositionTable::new(1 << 12);
+        let ctx = SearchContext {
+            deadline: Instant::now() + Duration::from_secs(600),
+            mode: SearchMode::Paranoid,
+            scorer: &scoring::turns_survived,
+            tt: &tt,
+            ordering: &MoveOrdering::new(&game),
+            interrupt: None,
+            stats: &Default::default(),
+            max_turn: 0,
+        };
+        let mut root = MaximizingNode::new(game.clone());
+        root.solve(&ctx, 2, &AlphaBeta::new(i64::MIN, i64::MAX), 1.0);
+
+        let mut next = game.clone();
+        next.execute_moves(Direction::Up, &[Direction::Left]);
+        let node = subtree(root, &next).unwrap();
+        assert!(same_position(&node.game, &next));
+        assert!(node.score.is_some());
+
+        let root = MaximizingNode::new(game.clone());
+        let mut later = next.clone();
+        later.execute_moves(Direction::Up, &[Direction::Left]);
+        assert!(subtree(root, &later).is_none());
+    }
+
+    #[test]
+    fn take_and_put() {
+        let game = parse_game(POSITION).unwrap();
+        let worker = Worker::start(
+            game.clone(),
+            scoring::turns_survived,
+            Arc::new(TranspositionTable::new(1 << 12)),
+            0,
+        );
+        thread::sleep(Duration::from_millis(100));
+        let root = worker.take(&game);
+        assert!(same_position(&root.game, &game));
+        assert!(!root.children.is_empty());
+        worker.put(root, 3);
+
+        // The background search hands the tree back at the next node, and
+        // continues from the depth the request completed
+        let start = Instant::now();
+        let root = worker.take(&game);
+        assert!(start.elapsed() < Duration::from_millis(50));
+        assert!(same_position(&root.game, &game));
+        assert!(worker.lock().depth >= 3);
+
+        assert!(!worker.finished());
+        worker.stop();
+        assert!(worker.finished());
+    }
+
+    #[test]
+    fn stop_when_idle() {
+        let game = parse_game(POSITION).unwrap();
+        let worker = Worker::start(
+            game,
+            scoring::turns_survived,
+            Arc::new(TranspositionTable::new(1 << 12)),
+            0,
+        );
+        thread::sleep(IDLE_TIMEOUT + Duration::from_millis(500));
+        assert!(worker.finished());
+    }
+}
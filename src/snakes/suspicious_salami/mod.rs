@@ -11,17 +11,19 @@ use rand::Rng;
 use crate::{
     log,
     logic::{self, Game},
-    protocol::{self, Customizations, ALL_DIRECTIONS},
+    protocol::{self, Customizations},
     Battlesnake,
 };
 
-use super::spaceheater3::util::{all_sensible_enemy_moves, certain_death};
+use super::spaceheater3::{
+    timing::TimeManager,
+    util::{all_sensible_enemy_moves, lost, sensible_moves},
+};
 
 pub const DEFAULT_COLOR: &str = "#FF5C75";
 pub const DEFAULT_HEAD: &str = "safe";
 pub const DEFAULT_TAIL: &str = "round-bum";
 const MAX_DEPTH: usize = 1000;
-const LATENCY_MARGIN: Duration = Duration::from_millis(100);
 
 pub struct Salami<S>
 where
@@ -29,6 +31,7 @@ where
 {
     scorer: S,
     customizations: Customizations,
+    clock: TimeManager,
 }
 
 impl<S> Battlesnake for Salami<S>
@@ -50,7 +53,8 @@ where
         Ok(())
     }
 
-    fn end(&self, _: &crate::protocol::Request) -> Result<(), String> {
+    fn end(&self, req: &crate::protocol::Request) -> Result<(), String> {
+        self.clock.end(&req.game.id);
         Ok(())
     }
 
@@ -58,11 +62,12 @@ where
         &self,
         req: &crate::protocol::Request,
     ) -> Result<crate::protocol::MoveResponse, String> {
-        let game = Game::from(req);
+        let game = Game::try_from(req)?;
         let start = Instant::now();
-        let deadline = start + game.timeout - LATENCY_MARGIN;
+        let deadline = self.clock.deadline(req, &game, start);
 
         let (best_dir, top_score) = self.solve(game, &deadline);
+        self.clock.moved(&req.game.id, start);
 
         Ok(protocol::MoveResponse {
             direction: best_dir,
@@ -82,7 +87,9 @@ where
                 color: DEFAULT_COLOR.into(),
                 head: DEFAULT_HEAD.into(),
                 tail: DEFAULT_TAIL.into(),
+                ..Default::default()
             }),
+            clock: Default::default(),
         }
     }
 
@@ -250,11 +257,12 @@ impl Max {
         if Instant::now() > *deadline {
             return None;
         }
-        if self.game.you.dead() || max_depth == 0 {
+        // Lost positions end the search like they do in the paranoid search
+        if lost(&self.game) || max_depth == 0 {
             log!(
-                "bound reached at turn {} (dead={}), returning {}",
+                "bound reached at turn {} (lost={}), returning {}",
                 self.game.turn,
-                self.game.you.dead(),
+                lost(&self.game),
                 self.score
             );
             return Some(self.score);
@@ -330,25 +338,13 @@ impl Max {
 
     fn generate_children(&mut self) {
         if self.children.len() == 0 {
-            for my_dir in ALL_DIRECTIONS {
-                let mut my_pos = self.game.you.head.neighbour(my_dir);
-                self.game.warp(&mut my_pos);
-                if !certain_death(&self.game, &self.game.you, &my_pos) {
-                    self.children.push(Min {
-                        children: vec![],
-                        score: self.score,
-                        visit_count: 1,
-                        my_move: my_dir,
-                    });
-                }
-            }
-            if self.children.len() == 0 {
-                // rejected all directions, but we still have to move because certain death.
+            // When every move is certain death, the enemy moves still decide who we take with us
+            for my_dir in sensible_moves(&self.game, &self.game.you) {
                 self.children.push(Min {
                     children: vec![],
                     score: self.score,
                     visit_count: 1,
-                    my_move: Direction::Up,
+                    my_move: my_dir,
                 });
             }
         } else {
@@ -0,0 +1,288 @@
+use std::{cmp, fmt::Display};
+
+use crate::{
+    logic::{self, Board, Point},
+    protocol::{self, Direction, ALL_DIRECTIONS},
+};
+
+use super::gamelogger;
+
+/// Mismatch lists the differences between the state predicted by the rules
+/// engine and the actual state of the next turn of a logged game.
+pub struct Mismatch {
+    pub turn: usize,
+    pub problems: Vec<String>,
+    pub diff: String,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "turn {} -> {}: {} mismatches\n",
+            self.turn,
+            self.turn + 1,
+            self.problems.len()
+        ))?;
+        for p in &self.problems {
+            f.write_fmt(format_args!("  {}\n", p))?;
+        }
+        f.write_str(&self.diff)
+    }
+}
+
+/// check_game replays every turn of a logged game through the rules engine,
+/// and returns the turns where the outcome differs from the logged game.
+pub fn check_game(game: &gamelogger::Game) -> Vec<Mismatch> {
+    let mut requests: Vec<&protocol::Request> = game.moves.iter().map(|(r, _)| r).collect();
+    if let Some(end) = &game.end_request {
+        requests.push(end);
+    }
+
+    requests
+        .windows(2)
+        .filter(|w| w[1].turn == w[0].turn + 1)
+        .filter_map(|w| check_turn(w[0], w[1]).err())
+        .collect()
+}
+
+/// check_turn applies the moves the snakes made between two consecutive
+/// requests. The moves of surviving snakes are inferred from their new
+/// heads, for snakes that died every move is tried. Food spawns are ignored.
+pub fn check_turn(before: &protocol::Request, after: &protocol::Request) -> Result<(), Mismatch> {
+    let invalid = |problem| Mismatch {
+        turn: before.turn,
+        problems: vec![problem],
+        diff: String::new(),
+    };
+    let game = logic::Game::try_from(before).map_err(invalid)?;
+    let actual = logic::Game::try_from(after).map_err(invalid)?;
+
+    // Snakes are numbered like in Game::try_from: you first, then the others
+    let snakes: Vec<&protocol::Snake> = std::iter::once(&before.you)
+        .chain(before.board.snakes.iter().filter(|s| s.id != before.you.id))
+        .collect();
+
+    let mut problems = vec![];
+    let mut candidates = vec![];
+    for snake in snakes.iter() {
+        match after.board.snakes.iter().find(|s| s.id == snake.id) {
+            Some(next) => match infer_move(&game, &snake.head, &next.head) {
+                Some(dir) => candidates.push(vec![dir]),
+                None => {
+                    problems.push(format!(
+                        "{} moved from {} to {}, which is not a single move",
+                        snake.name, snake.head, next.head
+                    ));
+                    candidates.push(vec![Direction::Up]);
+                }
+            },
+            None => candidates.push(ALL_DIRECTIONS.to_vec()),
+        }
+    }
+
+    let mut best: Option<(Vec<String>, logic::Game)> = None;
+    for moves in combinations(&candidates) {
+        let mut predicted = game.clone();
+        predicted.execute_moves(moves[0], &moves[1..]);
+        let mismatches = compare(&game, &predicted, &actual.board, after, &snakes);
+        let better = match &best {
+            Some((m, _)) => mismatches.len() < m.len(),
+            None => true,
+        };
+        if better {
+            best = Some((mismatches, predicted));
+        }
+        if best.as_ref().unwrap().0.is_empty() {
+            break;
+        }
+    }
+
+    let (mut mismatches, predicted) = best.unwrap();
+    problems.append(&mut mismatches);
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    Err(Mismatch {
+        turn: before.turn,
+        problems,
+        diff: board_diff(
+            &game.board,
+            &actual.board,
+            &predicted.board,
+        ),
+    })
+}
+
+fn infer_move(game: &logic::Game, head: &Point, next_head: &Point) -> Option<Direction> {
+    ALL_DIRECTIONS.into_iter().find(|d| {
+        let mut p = head.neighbour(*d);
+        game.warp(&mut p);
+        &p == next_head
+    })
+}
+
+fn combinations(candidates: &Vec<Vec<Direction>>) -> Vec<Vec<Direction>> {
+    let mut res = vec![vec![]];
+    for options in candidates {
+        let mut next = vec![];
+        for combo in res.iter() {
+            for dir in options {
+                let mut c: Vec<Direction> = combo.clone();
+                c.push(*dir);
+                next.push(c);
+            }
+        }
+        res = next;
+    }
+    res
+}
+
+fn compare(
+    game: &logic::Game,
+    predicted: &logic::Game,
+    actual: &Board,
+    after: &protocol::Request,
+    snakes: &Vec<&protocol::Snake>,
+) -> Vec<String> {
+    let mut problems = vec![];
+
+    for (id, snake) in snakes.iter().enumerate() {
+        let predicted_snake = std::iter::once(&predicted.you)
+            .chain(predicted.others.iter())
+            .find(|s| s.id as usize == id && !s.dead());
+        let actual_snake = after.board.snakes.iter().find(|s| s.id == snake.id);
+
+        match (predicted_snake, actual_snake) {
+            (Some(_), None) => problems.push(format!("{} died, but survived", snake.name)),
+            (None, Some(_)) => problems.push(format!("{} survived, but died", snake.name)),
+            (Some(p), Some(a)) => {
+                if p.body != a.body {
+                    problems.push(format!(
+                        "{} body is {:?}, expected {:?}",
+                        snake.name,
+                        p.body.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+                        a.body.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+                    ));
+                }
+                if p.health as isize != a.health {
+                    problems.push(format!(
+                        "{} health is {}, expected {}",
+                        snake.name, p.health, a.health
+                    ));
+                }
+                if p.length != a.length {
+                    problems.push(format!(
+                        "{} length is {}, expected {}",
+                        snake.name, p.length, a.length
+                    ));
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    for x in 0..actual.width() as i8 {
+        for y in 0..actual.height() as i8 {
+            let p = Point { x, y };
+            let (hazards, expected_hazards) =
+                (predicted.board.hazard_count(&p), actual.hazard_count(&p));
+            if hazards != expected_hazards {
+                problems.push(format!(
+                    "{} has {} hazards, expected {}",
+                    p, hazards, expected_hazards
+                ));
+            }
+
+            let food = predicted.board.get(&p).has_food();
+            let expected_food = actual.get(&p).has_food();
+            if food && !expected_food {
+                problems.push(format!("food at {} should have been eaten", p));
+            } else if !food && expected_food && game.board.get(&p).has_food() {
+                problems.push(format!("food at {} was eaten, but it should remain", p));
+            }
+        }
+    }
+
+    problems
+}
+
+/// board_diff draws the board before the moves, the actual next board and the
+/// predicted next board next to each other. Tiles that differ are marked with
+/// a ^ in the last column, spawned food is not counted as a difference.
+fn board_diff(before: &Board, actual: &Board, predicted: &Board) -> String {
+    let w = cmp::max(before.width() as usize, "predicted".len());
+    let mut res = format!(
+        "{:w$}   {:w$}   {:w$}   diff\n",
+        "before", "actual", "predicted"
+    );
+    for ny in 0..actual.height() {
+        let y = (actual.height() - 1 - ny) as i8;
+        let row = |b: &Board| {
+            (0..b.width() as i8)
+                .map(|x| b.get(&Point { x, y }).to_string())
+                .collect::<String>()
+        };
+        let diff: String = (0..actual.width() as i8)
+            .map(|x| {
+                let p = Point { x, y };
+                let (a, p2, b) = (actual.get(&p), predicted.get(&p), before.get(&p));
+                let spawned_food = a.clear_food() == p2 && !b.has_food();
+                if a == p2 || spawned_food {
+                    '.'
+                } else {
+                    '^'
+                }
+            })
+            .collect();
+        res.push_str(&format!(
+            "{:w$}   {:w$}   {:w$}   {}\n",
+            row(before),
+            row(actual),
+            row(predicted),
+            diff
+        ));
+    }
+    res
+}
+
+#[cfg(test)]
+fn load(bytes: &[u8]) -> gamelogger::Game {
+    gamelogger::Game::load(&mut &bytes[..]).unwrap()
+}
+
+#[test]
+fn sample_games_conform() {
+    for game in [
+        load(include_bytes!("../../sample_games/2_players_11x11.json.gz")),
+        load(include_bytes!("../../sample_games/3_players_11x11.json.gz")),
+        load(include_bytes!(
+            "../../sample_games/4_players_11x11_wrapped_islands.json.gz"
+        )),
+        load(include_bytes!("../../sample_games/8_players_11x11.json.gz")),
+        load(include_bytes!("../../sample_games/8_players_25x25.json.gz")),
+    ] {
+        let mismatches = check_game(&game);
+        for m in mismatches.iter() {
+            println!("{}", m);
+        }
+        assert!(mismatches.is_empty());
+    }
+}
+
+#[test]
+fn report_mismatches() {
+    let game = load(include_bytes!("../../sample_games/3_players_11x11.json.gz"));
+    let before = &game.moves[10].0;
+    let mut after = game.moves[11].0.clone();
+    assert!(check_turn(before, &after).is_ok());
+
+    after.board.snakes[0].health -= 5;
+    after.board.hazards.push(Point { x: 0, y: 0 });
+    let mismatch = check_turn(before, &after).err().unwrap();
+    assert_eq!(mismatch.turn, before.turn);
+    assert_eq!(mismatch.problems.len(), 2);
+    assert!(mismatch.problems[0].contains("health"));
+    assert!(mismatch.problems[1].contains("hazards"));
+    assert!(mismatch.diff.contains('^'));
+}
@@ -8,7 +8,11 @@ use flate2::write::GzEncoder;
 use flate2::Compression;
 use serde::{Deserialize, Serialize};
 
-use crate::{logic, protocol, Battlesnake};
+use crate::{
+    logic, protocol,
+    snakes::{SearchReport, TreeFormat},
+    Battlesnake,
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct Game {
@@ -16,10 +20,21 @@ pub struct Game {
     pub start_request: protocol::Request,
     pub end_request: Option<protocol::Request>,
     pub moves: Vec<(protocol::Request, Option<protocol::MoveResponse>)>,
+    // Search reports of the snake that played, by turn
+    #[serde(default)]
+    pub reports: HashMap<usize, SearchReport>,
 }
 
 type Result = std::result::Result<String, String>;
 
+/// TreeDump asks a replay to save the search tree of a turn to a file.
+pub struct TreeDump {
+    pub turn: usize,
+    pub format: TreeFormat,
+    pub depth: usize,
+    pub width: usize,
+}
+
 fn into_result<T, E: ToString>(v: std::result::Result<T, E>) -> std::result::Result<T, String> {
     match v {
         Ok(t) => Ok(t),
@@ -38,6 +53,7 @@ impl Game {
             start_request,
             end_request: None,
             moves: Vec::new(),
+            reports: HashMap::new(),
         }
     }
 
@@ -61,6 +77,7 @@ impl Game {
         start_turn: Option<usize>,
         end_turn: Option<usize>,
         time_per_turn: Option<usize>,
+        tree_dump: Option<&TreeDump>,
     ) {
         let mut start_req = self.start_request.clone();
         if let Some(millis) = time_per_turn {
@@ -89,7 +106,10 @@ impl Game {
                 if let Some(millis) = time_per_turn {
                     req.game.timeout = millis as isize;
                 }
-                println!("{}", logic::Game::from(&req));
+                match logic::Game::try_from(&req) {
+                    Ok(game) => println!("{}", game),
+                    Err(e) => println!("{}", e),
+                }
                 let start = Instant::now();
                 let res = snake.make_move(&req);
                 println!(
@@ -98,7 +118,19 @@ impl Game {
                     res.map(|r| r.direction.to_string())
                         .unwrap_or("no response".to_string()),
                     start.elapsed().as_millis(),
-                )
+                );
+                if let Some(report) = self.reports.get(&req.turn) {
+                    print!("recorded search: {}", report);
+                }
+                if let Some(report) = snake.last_report(&req.game.id) {
+                    print!("replayed search: {}", report);
+                }
+                if let Some(dump) = tree_dump.filter(|d| d.turn == req.turn) {
+                    match dump.save(snake, &req) {
+                        Ok(filename) => println!("saved search tree in {}", filename),
+                        Err(e) => println!("warning: failed to save search tree: {}", e),
+                    }
+                }
             }
         }
         if let Some(end_request) = &self.end_request {
@@ -130,6 +162,26 @@ impl Game {
     }
 }
 
+impl TreeDump {
+    fn save(&self, snake: &dyn Battlesnake, req: &protocol::Request) -> Result {
+        let tree = snake
+            .export_tree(&req.game.id, self.format, self.depth, self.width)
+            .ok_or("snake has no search tree".to_string())?;
+        let extension = match self.format {
+            TreeFormat::Dot => "dot",
+            TreeFormat::Json => "json",
+        };
+        let filename = format!(
+            "tree_{}_{}.{}",
+            sanitize_filename::sanitize(&req.game.id),
+            req.turn,
+            extension
+        );
+        into_result(std::fs::write(&filename, tree))?;
+        Ok(filename)
+    }
+}
+
 pub struct GameLogger {
     open_games: sync::Mutex<HashMap<(String, String), Game>>,
 }
@@ -166,12 +218,16 @@ impl GameLogger {
         &mut self,
         request: &protocol::Request,
         response: Option<&protocol::MoveResponse>,
+        report: Option<SearchReport>,
     ) {
         let mut open_games = self.open_games.lock().unwrap();
 
         if let Some(game) = open_games.get_mut(&game_id(&request)) {
             game.moves
                 .push((request.clone(), response.map(|r| r.clone())));
+            if let Some(report) = report {
+                game.reports.insert(request.turn, report);
+            }
         } else {
             println!(
                 "move for unknown game {} {}",
@@ -1,64 +1,514 @@
 use std::{
-    collections::HashSet,
-    process::{Command, ExitStatus},
+    cmp::Reverse,
+    collections::HashMap,
+    sync::{mpsc::channel, Arc},
+    thread,
+    time::{Duration, Instant},
 };
 
-pub fn run_game(args: &Vec<String>, base_url: &str, snake_names: Vec<String>) -> Vec<String> {
-    let mut cmd_args = vec![String::from("play")];
-    cmd_args.append(&mut args.clone());
-    for snake_name in &snake_names {
-        cmd_args.push("-n".to_string());
-        cmd_args.push(snake_name.clone());
-        cmd_args.push("-u".to_string());
-        cmd_args.push(format!("http://{}/{}/", base_url, snake_name));
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    logic::{
+        game::{self, GameMode},
+        maps::MapKind,
+        Game,
+    },
+    protocol::{self, Direction},
+    Battlesnake,
+};
+
+/// GameSettings describes the games played by run_game.
+#[derive(Clone)]
+pub struct GameSettings {
+    pub width: usize,
+    pub height: usize,
+    pub ruleset: protocol::Ruleset,
+    pub map: String,
+    pub timeout: Duration,
+    // Games that take longer than this are stopped, the survivors share the
+    // first place
+    pub max_turns: usize,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            width: 11,
+            height: 11,
+            ruleset: protocol::Ruleset::default(),
+            map: "standard".to_string(),
+            timeout: Duration::from_millis(500),
+            max_turns: 10000,
+        }
     }
+}
+
+#[derive(Clone)]
+pub struct Player {
+    pub name: String,
+    pub snake: Arc<dyn Battlesnake + Sync + Send>,
+}
 
-    match Command::new("battlesnake")
-        .args(cmd_args.as_slice())
-        .output()
+pub struct GameResult {
+    pub seed: u64,
+    pub turns: usize,
+    // Names of the players and their place, the winner first. Players that
+    // were eliminated on the same turn or survived share a place.
+    pub placements: Vec<(String, usize)>,
+    // The turn on which a player was eliminated
+    pub eliminations: HashMap<String, usize>,
+    // The number of moves a player didn't answer in time
+    pub timeouts: HashMap<String, usize>,
+}
+
+/// new_game places the players on a new board and sets up the map. Player
+/// i becomes the snake with id i, the first player is you.
+pub fn new_game(settings: &GameSettings, players: &[Player], seed: u64) -> Result<Game, String> {
+    if players.is_empty() {
+        return Err("a game needs at least one player".to_string());
+    }
+    for (i, player) in players.iter().enumerate() {
+        if players[..i].iter().any(|p| p.name == player.name) {
+            return Err(format!("{} is playing more than once", player.name));
+        }
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let game_mode = GameMode::from_name(&settings.ruleset.name).unwrap_or(GameMode::Standard);
+    let map = game::map_kind(game_mode, &settings.map).unwrap_or(MapKind::Standard);
+    if !map
+        .get()
+        .fits(settings.width as isize, settings.height as isize)
     {
-        Ok(res) => {
-            if !ExitStatus::success(&res.status) {
-                println!("{}", String::from_utf8_lossy(&res.stdout));
-                println!("{}", String::from_utf8_lossy(&res.stderr));
-                panic!(
-                    "command failed: battlesnake {}: exit code {}",
-                    cmd_args.join(" "),
-                    res.status
-                );
+        return Err(format!(
+            "{} has no layout for {}x{} boards",
+            map.get().name(),
+            settings.width,
+            settings.height
+        ));
+    }
+    let spawns = map.get().spawn_points(
+        settings.width as isize,
+        settings.height as isize,
+        players.len(),
+        &mut rng,
+    );
+    if spawns.len() < players.len() {
+        return Err(format!(
+            "{} has room for {} snakes, not {}",
+            map.get().name(),
+            spawns.len(),
+            players.len()
+        ));
+    }
+
+    let snakes: Vec<protocol::Snake> = players
+        .iter()
+        .zip(spawns.iter())
+        .map(|(player, p)| {
+            let info = player.snake.snake_info();
+            protocol::Snake {
+                id: player.name.clone(),
+                name: player.name.clone(),
+                health: 100,
+                body: vec![*p; 3].into(),
+                latency: "0".to_string(),
+                head: *p,
+                length: 3,
+                shout: String::new(),
+                squad: String::new(),
+                customizations: protocol::Customizations {
+                    color: info.color,
+                    head: info.head,
+                    tail: info.tail,
+                    ..Default::default()
+                },
+                extra: protocol::Extra::default(),
+            }
+        })
+        .collect();
+
+    let request = protocol::Request {
+        game: protocol::Game {
+            id: format!("local-{}", seed),
+            ruleset: settings.ruleset.clone(),
+            map: settings.map.clone(),
+            timeout: settings.timeout.as_millis() as isize,
+            source: "local".to_string(),
+            extra: protocol::Extra::default(),
+        },
+        turn: 0,
+        board: protocol::Board {
+            height: settings.height,
+            width: settings.width,
+            food: vec![],
+            hazards: vec![],
+            snakes: snakes.clone(),
+            extra: protocol::Extra::default(),
+        },
+        you: snakes[0].clone(),
+        extra: protocol::Extra::default(),
+    };
+    let mut game = Game::try_from(&request)?;
+    game.rules.seed = Some(seed);
+    game.map.get().setup_board(&mut game, &mut rng);
+    Ok(game)
+}
+
+/// run_game plays a game between the players, calling them in-process. The
+/// game is fully determined by the seed and the moves of the players.
+pub fn run_game(
+    settings: &GameSettings,
+    players: &[Player],
+    seed: u64,
+) -> Result<GameResult, String> {
+    let mut game = new_game(settings, players, seed)?;
+    let mut result = GameResult {
+        seed,
+        turns: 0,
+        placements: vec![],
+        eliminations: HashMap::new(),
+        timeouts: HashMap::new(),
+    };
+
+    let mut last_requests: Vec<protocol::Request> = (0..players.len())
+        .map(|id| game.request_for(id as u8).unwrap())
+        .collect();
+    for (player, req) in players.iter().zip(last_requests.iter()) {
+        if let Err(e) = player.snake.start(req) {
+            println!("{} failed to start: {}", player.name, e);
+        }
+    }
+
+    // Snakes that time out repeat their last move, like in the official engine
+    let mut last_moves = vec![Direction::Up; players.len()];
+    let mut latencies = vec![Duration::ZERO; players.len()];
+    loop {
+        // Game only tracks the moves of others while you are alive
+        if game.you.dead() && !game.others.is_empty() {
+            game.you = game.others.remove(0);
+        }
+
+        let alive = alive_snakes(&game);
+        let finished = if players.len() == 1 {
+            alive.is_empty()
+        } else {
+            alive.len() <= 1
+        };
+        if finished || game.turn >= settings.max_turns {
+            break;
+        }
+
+        for &id in alive.iter() {
+            last_requests[id] = game.request_for(id as u8).unwrap();
+        }
+        collect_moves(
+            settings,
+            players,
+            &alive,
+            &last_requests,
+            &mut last_moves,
+            &mut latencies,
+            &mut result.timeouts,
+        );
+
+        // Like the official engine, the next requests report how long the
+        // snakes took to answer
+        let metadata = Arc::make_mut(&mut game.metadata);
+        for &id in alive.iter() {
+            metadata.snakes[id].latency = latencies[id].as_millis().to_string();
+        }
+
+        let others: Vec<_> = game
+            .others
+            .iter()
+            .map(|s| last_moves[s.id as usize])
+            .collect();
+        game.execute_moves(last_moves[game.you.id as usize], &others);
+
+        let survivors = alive_snakes(&game);
+        for id in alive {
+            if !survivors.contains(&id) {
+                result
+                    .eliminations
+                    .insert(players[id].name.clone(), game.turn);
             }
-            let output = String::from_utf8_lossy(&res.stderr);
-            let mut deaths = vec![];
-            let mut alive: HashSet<String> = HashSet::from_iter(snake_names.into_iter());
-            let mut turn = 0;
-            for line in output.split("\n") {
-                if let Some(p) = line.find("Snakes Alive: [") {
-                    let p = p + "Snakes Alive: [".len();
-                    let strlen = line[p..].find("],").unwrap_or(0);
-                    let mut newly_dead = alive.clone();
-                    for snake in line[p..p + strlen].split(", ").map(|s| s.to_string()) {
-                        newly_dead.remove(&snake);
-                    }
-                    for snake in newly_dead {
-                        println!("{} has died (turn {})", snake, turn);
-                        alive.remove(&snake);
-                        deaths.push(snake);
-                    }
-                    turn += 1;
+        }
+    }
+    result.turns = game.turn;
+
+    // Survivors first, then the other players from last to first eliminated
+    let mut placements: Vec<(usize, usize)> = (0..players.len())
+        .map(|id| {
+            let eliminated = result.eliminations.get(&players[id].name);
+            (id, eliminated.map_or(usize::MAX, |turn| *turn))
+        })
+        .collect();
+    placements.sort_by_key(|(_, eliminated)| Reverse(*eliminated));
+    for (i, (id, eliminated)) in placements.iter().enumerate() {
+        let place = match result.placements.last() {
+            Some((_, place)) if placements[i - 1].1 == *eliminated => *place,
+            _ => i,
+        };
+        result.placements.push((players[*id].name.clone(), place));
+    }
+
+    let last_request = protocol::Request::from(&game);
+    for (id, player) in players.iter().enumerate() {
+        let req = game
+            .request_for(id as u8)
+            .unwrap_or_else(|| protocol::Request {
+                you: last_requests[id].you.clone(),
+                ..last_request.clone()
+            });
+        if let Err(e) = player.snake.end(&req) {
+            println!("{} failed to end: {}", player.name, e);
+        }
+    }
+
+    Ok(result)
+}
+
+fn alive_snakes(game: &Game) -> Vec<usize> {
+    std::iter::once(&game.you)
+        .chain(game.others.iter())
+        .filter(|s| !s.dead())
+        .map(|s| s.id as usize)
+        .collect()
+}
+
+// collect_moves asks all snakes for their move at the same time, and waits
+// for their answers until the timeout expires. Snakes that time out get the
+// timeout as their latency. Their late answers are thrown away, but they're
+// still waited for so the threads of slow snakes don't pile up.
+fn collect_moves(
+    settings: &GameSettings,
+    players: &[Player],
+    alive: &[usize],
+    requests: &[protocol::Request],
+    moves: &mut [Direction],
+    latencies: &mut [Duration],
+    timeouts: &mut HashMap<String, usize>,
+) {
+    let start = Instant::now();
+    let deadline = start + settings.timeout;
+    let (tx, rx) = channel();
+    let mut handles = Vec::with_capacity(alive.len());
+    for &id in alive.iter() {
+        let snake = players[id].snake.clone();
+        let req = requests[id].clone();
+        let tx = tx.clone();
+        handles.push(thread::spawn(move || {
+            // Nobody is listening anymore when this snake answers late
+            _ = tx.send((id, snake.make_move(&req)));
+        }));
+    }
+    drop(tx);
+
+    let mut waiting = alive.to_vec();
+    while !waiting.is_empty() {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(timeout) {
+            Ok((id, res)) => {
+                waiting.retain(|v| *v != id);
+                latencies[id] = start.elapsed();
+                match res {
+                    Ok(res) => moves[id] = res.direction,
+                    Err(e) => println!("{} failed to move: {}", players[id].name, e),
                 }
             }
-            for snake in alive {
-                println!("{} has won", snake);
-                deaths.push(snake);
+            Err(_) => break,
+        }
+    }
+    for &id in waiting.iter() {
+        latencies[id] = settings.timeout;
+        *timeouts.entry(players[id].name.clone()).or_insert(0) += 1;
+    }
+    drop(rx);
+    for handle in handles {
+        _ = handle.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex, time::Duration};
+
+    use super::*;
+    use crate::{logic::Point, protocol::ALL_DIRECTIONS, util::gauntlet::RemoteSnake};
+
+    // Takes the first direction that doesn't run into something
+    struct FirstSafeMove {
+        delay: Duration,
+        // The latency reported in every move request
+        latencies: Mutex<Vec<String>>,
+    }
+
+    impl FirstSafeMove {
+        fn new(delay: Duration) -> Self {
+            Self {
+                delay,
+                latencies: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    impl Battlesnake for FirstSafeMove {
+        fn snake_info(&self) -> protocol::SnakeInfo {
+            protocol::SnakeInfo {
+                apiversion: "1".to_string(),
+                author: String::new(),
+                color: "#123456".to_string(),
+                head: String::new(),
+                tail: String::new(),
+                version: String::new(),
             }
+        }
 
-            deaths.reverse();
-            return deaths;
+        fn start(&self, _: &protocol::Request) -> Result<(), String> {
+            Ok(())
         }
-        Err(error) => panic!(
-            "command failed: battlesnake {}: {}",
-            cmd_args.join(" "),
-            error
-        ),
+
+        fn end(&self, _: &protocol::Request) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn make_move(&self, req: &protocol::Request) -> Result<protocol::MoveResponse, String> {
+            self.latencies.lock().unwrap().push(req.you.latency.clone());
+            thread::sleep(self.delay);
+            let game = Game::try_from(req)?;
+            let direction = ALL_DIRECTIONS
+                .into_iter()
+                .find(|d| {
+                    let mut p: Point = game.you.head.neighbour(*d);
+                    game.warp(&mut p);
+                    game.board.get(&p).is_safe()
+                })
+                .unwrap_or(Direction::Up);
+            Ok(protocol::MoveResponse {
+                direction,
+                shout: String::new(),
+            })
+        }
+    }
+
+    fn players(count: usize) -> Vec<Player> {
+        (0..count)
+            .map(|i| Player {
+                name: format!("snake{}", i),
+                snake: Arc::new(FirstSafeMove::new(Duration::ZERO)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn games_are_reproducible() {
+        let settings = GameSettings::default();
+        let players = players(4);
+
+        let game = new_game(&settings, &players, 42).unwrap();
+        assert_eq!(game.others.len(), 3);
+        assert_eq!(game.rules.seed, Some(42));
+
+        let first = run_game(&settings, &players, 42).unwrap();
+        let second = run_game(&settings, &players, 42).unwrap();
+        assert_eq!(first.turns, second.turns);
+        assert_eq!(first.placements, second.placements);
+        assert_eq!(first.eliminations, second.eliminations);
+        assert_eq!(first.placements.len(), 4);
+        assert!(first.eliminations.len() >= 3);
+        assert!(!first.eliminations.contains_key(&first.placements[0].0));
+    }
+
+    #[test]
+    fn survivors_share_first_place() {
+        let settings = GameSettings {
+            max_turns: 3,
+            ..Default::default()
+        };
+        let result = run_game(&settings, &players(4), 42).unwrap();
+        assert_eq!(result.turns, 3);
+        assert!(result.placements.iter().all(|(_, place)| *place == 0));
+    }
+
+    #[test]
+    fn maps_and_game_modes() {
+        let mut settings = GameSettings::default();
+        settings.ruleset.name = "wrapped".to_string();
+        settings.map = "royale".to_string();
+        settings.ruleset.settings.royale.shrink_every_n_turns = 5;
+        settings.max_turns = 60;
+
+        let result = run_game(&settings, &players(2), 3).unwrap();
+        assert!(result.turns <= 60);
+
+        settings = GameSettings {
+            width: 19,
+            height: 21,
+            map: "arcade_maze".to_string(),
+            ..GameSettings::default()
+        };
+        let game = new_game(&settings, &players(2), 3).unwrap();
+        assert_eq!(game.map, MapKind::ArcadeMaze);
+        let result = run_game(&settings, &players(1), 3).unwrap();
+        assert_eq!(result.eliminations.len(), 1);
+    }
+
+    #[test]
+    fn slow_snakes_time_out() {
+        let settings = GameSettings {
+            timeout: Duration::from_millis(20),
+            max_turns: 3,
+            ..GameSettings::default()
+        };
+        let mut players = players(2);
+        let slow = Arc::new(FirstSafeMove::new(Duration::from_millis(200)));
+        players[1].snake = slow.clone();
+
+        let result = run_game(&settings, &players, 1).unwrap();
+        assert_eq!(result.timeouts.get("snake0"), None);
+        assert!(result.timeouts["snake1"] > 0);
+        // No request is still running after the game
+        assert_eq!(Arc::strong_count(&slow), 2);
+    }
+
+    #[test]
+    fn latency_is_reported() {
+        let settings = GameSettings {
+            max_turns: 4,
+            ..GameSettings::default()
+        };
+        let mut players = players(2);
+        let slow = Arc::new(FirstSafeMove::new(Duration::from_millis(10)));
+        players[1].snake = slow.clone();
+
+        run_game(&settings, &players, 1).unwrap();
+        let latencies = slow.latencies.lock().unwrap();
+        assert_eq!(latencies[0], "0");
+        assert!(latencies.len() > 1);
+        for latency in &latencies[1..] {
+            assert!(latency.parse::<u64>().unwrap() >= 10);
+        }
+    }
+
+    #[test]
+    fn remote_snakes() {
+        let mut snakes: HashMap<String, Box<dyn Battlesnake + Sync + Send>> = HashMap::new();
+        snakes.insert(
+            "remote".to_string(),
+            Box::new(FirstSafeMove::new(Duration::ZERO)),
+        );
+        let server = super::super::webserver::Webserver::new(snakes);
+
+        let mut players = players(2);
+        let url = format!("http://{}/remote/", server.address());
+        players[1].snake = Arc::new(RemoteSnake::new(&url).unwrap());
+        assert_eq!(players[1].snake.snake_info().color, "#123456");
+
+        let local = run_game(&GameSettings::default(), &self::players(2), 5).unwrap();
+        let remote = run_game(&GameSettings::default(), &players, 5).unwrap();
+        assert_eq!(local.placements, remote.placements);
+        assert_eq!(local.turns, remote.turns);
     }
 }
@@ -5,17 +5,22 @@ use std::{
     time::Instant,
 };
 
-use crate::{snakes, util::gauntlet::webserver::Webserver, Battlesnake};
+use crate::{snakes, Battlesnake};
 
 use self::{generation::next_generation, report::write_report};
 
-mod gamerunner;
+pub mod gamerunner;
 mod generation;
 mod names;
 mod pairing;
+mod remote;
 mod report;
+#[cfg(test)]
 mod webserver;
 
+pub use gamerunner::{GameResult, GameSettings, Player};
+pub use remote::RemoteSnake;
+
 const SCORES: [usize; 4] = [4, 2, 1, 0];
 
 pub trait RandomConfig {
@@ -33,7 +38,7 @@ pub trait GeneticConfig: ToString + Sync + Send {
 pub struct Gauntlet {
     configs: HashMap<String, Box<dyn GeneticConfig>>,
     generation: usize,
-    battlesnake_cli_args: Vec<String>,
+    settings: GameSettings,
 }
 
 #[derive(Clone)]
@@ -54,11 +59,11 @@ impl<'a> Score<'a> {
 }
 
 impl Gauntlet {
-    pub fn new(cli_args: &[&str]) -> Self {
+    pub fn new(settings: GameSettings) -> Self {
         Self {
             configs: HashMap::new(),
             generation: 0,
-            battlesnake_cli_args: cli_args.iter().map(|v| v.to_string()).collect(),
+            settings,
         }
     }
 
@@ -128,11 +133,14 @@ impl Gauntlet {
         let game_count = games.len();
         let (result_tx, result_rx) = channel();
 
-        let mut snakes: HashMap<_, _> = Self::reference_snakes();
+        let mut snakes: HashMap<String, Arc<dyn Battlesnake + Sync + Send>> =
+            Self::reference_snakes()
+                .into_iter()
+                .map(|(name, snake)| (name, Arc::from(snake)))
+                .collect();
         for (snake_name, cfg) in &self.configs {
-            snakes.insert(snake_name.clone(), cfg.battlesnake());
+            snakes.insert(snake_name.clone(), Arc::from(cfg.battlesnake()));
         }
-        let server = Webserver::new(snakes);
 
         println!(
             "Starting generation {}, running {} games",
@@ -141,9 +149,9 @@ impl Gauntlet {
         let games = Arc::new(Mutex::new(games));
         let mut threads = vec![];
         for t in 0..concurrent_games {
-            let base_url = server.address().to_string();
             let result_tx = result_tx.clone();
-            let cli_args = self.battlesnake_cli_args.clone();
+            let settings = self.settings.clone();
+            let snakes = snakes.clone();
             let games = games.clone();
             threads.push(thread::spawn(move || {
                 loop {
@@ -156,11 +164,29 @@ impl Gauntlet {
                     if game == None {
                         break;
                     }
-                    let game = game.unwrap();
-
-                    println!("Running game: {}", game.join(" vs "));
-                    let result = gamerunner::run_game(&cli_args, base_url.as_str(), game);
-                    result_tx.send(result).unwrap();
+                    let players: Vec<Player> = game
+                        .unwrap()
+                        .into_iter()
+                        .map(|name| Player {
+                            snake: snakes[&name].clone(),
+                            name,
+                        })
+                        .collect();
+
+                    let seed = rand::random();
+                    println!(
+                        "Running game: {} (seed {})",
+                        players
+                            .iter()
+                            .map(|p| p.name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(" vs "),
+                        seed
+                    );
+                    match gamerunner::run_game(&settings, &players, seed) {
+                        Ok(result) => result_tx.send(result.placements).unwrap(),
+                        Err(e) => println!("game with seed {} failed: {}", seed, e),
+                    }
                 }
                 println!("games finished, thread {} returning", t);
             }));
@@ -172,7 +198,7 @@ impl Gauntlet {
         while let Ok(result) = result_rx.recv() {
             result_count += 1;
             println!("Received results for game {}/{}", result_count, game_count);
-            for (rank, snake) in result.into_iter().enumerate() {
+            for (snake, rank) in result.into_iter() {
                 let s = scores.get_mut(&snake).unwrap();
                 s.points += SCORES[rank];
                 s.games_played += 1;
@@ -0,0 +1,125 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use crate::{protocol, Battlesnake};
+
+/// RemoteSnake plays a snake that is served over HTTP, so snakes that aren't
+/// part of this crate can play in local games.
+pub struct RemoteSnake {
+    // host:port
+    host: String,
+    // Path of the snake on the server, ending in a /
+    path: String,
+}
+
+impl RemoteSnake {
+    /// new parses a url of the form http://host:port/path/
+    pub fn new(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("unsupported url: {}, only http:// is supported", url))?;
+        let (host, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, "/"),
+        };
+        let host = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{}:80", host)
+        };
+        let path = if path.ends_with('/') {
+            path.to_string()
+        } else {
+            format!("{}/", path)
+        };
+        Ok(Self { host, path })
+    }
+
+    fn call(
+        &self,
+        endpoint: &str,
+        body: Option<String>,
+        timeout: Duration,
+    ) -> Result<String, String> {
+        let mut stream = TcpStream::connect(&self.host).map_err(|e| e.to_string())?;
+        stream
+            .set_read_timeout(Some(timeout))
+            .map_err(|e| e.to_string())?;
+
+        // HTTP/1.0 keeps the server from using chunked responses
+        let request = match body {
+            Some(body) => format!(
+                "POST {}{} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                self.path,
+                endpoint,
+                self.host,
+                body.len(),
+                body
+            ),
+            None => format!(
+                "GET {}{} HTTP/1.0\r\nHost: {}\r\n\r\n",
+                self.path, endpoint, self.host
+            ),
+        };
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| e.to_string())?;
+
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .map_err(|e| e.to_string())?;
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| format!("invalid response from {}", self.host))?;
+        let status = head.split(' ').nth(1).unwrap_or("");
+        if !status.starts_with('2') {
+            return Err(format!(
+                "{}{}: status {}: {}",
+                self.path, endpoint, status, body
+            ));
+        }
+        Ok(body.to_string())
+    }
+
+    fn post(&self, endpoint: &str, req: &protocol::Request) -> Result<String, String> {
+        let body = serde_json::to_string(req).map_err(|e| e.to_string())?;
+        // Leave some room for the network, the game runner enforces the timeout
+        let timeout = Duration::from_millis(req.game.timeout.max(0) as u64) * 2;
+        self.call(endpoint, Some(body), timeout)
+    }
+}
+
+impl Battlesnake for RemoteSnake {
+    fn snake_info(&self) -> protocol::SnakeInfo {
+        self.call("", None, Duration::from_secs(5))
+            .and_then(|body| serde_json::from_str(&body).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                println!("failed to get snake info from {}: {}", self.host, e);
+                protocol::SnakeInfo {
+                    apiversion: "1".to_string(),
+                    author: String::new(),
+                    color: String::new(),
+                    head: String::new(),
+                    tail: String::new(),
+                    version: String::new(),
+                }
+            })
+    }
+
+    fn start(&self, req: &protocol::Request) -> Result<(), String> {
+        self.post("start", req).map(|_| ())
+    }
+
+    fn end(&self, req: &protocol::Request) -> Result<(), String> {
+        self.post("end", req).map(|_| ())
+    }
+
+    fn make_move(&self, req: &protocol::Request) -> Result<protocol::MoveResponse, String> {
+        let body = self.post("move", req)?;
+        serde_json::from_str(&body).map_err(|e| e.to_string())
+    }
+}
@@ -2,6 +2,7 @@ use std::io::Read;
 
 use rouille::{Request, Response, ResponseBody};
 
+pub mod conformance;
 pub mod gamelogger;
 pub mod gauntlet;
 pub mod invert;
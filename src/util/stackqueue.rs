@@ -1,3 +1,7 @@
+use std::collections::VecDeque;
+
+/// StackDequeue is a ring buffer of CAP elements that lives on the stack.
+/// Elements pushed while it is full spill over into a VecDeque on the heap.
 pub struct StackDequeue<T, const CAP: usize>
 where
     T: Default + Copy,
@@ -6,6 +10,8 @@ where
     head: usize,
     tail: usize,
     count: usize,
+    // Elements in spill are always newer than the ones in data
+    spill: VecDeque<T>,
 }
 
 impl<T, const CAP: usize> StackDequeue<T, CAP>
@@ -18,6 +24,7 @@ where
             head: 0,
             tail: 0,
             count: 0,
+            spill: VecDeque::new(),
         }
     }
 
@@ -26,12 +33,12 @@ where
     }
 
     pub fn len(&self) -> usize {
-        self.count
+        self.count + self.spill.len()
     }
 
     pub fn pop_front(&mut self) -> Option<T> {
         if self.count == 0 {
-            None
+            self.spill.pop_front()
         } else {
             let idx = self.head;
             self.head += 1;
@@ -46,7 +53,9 @@ where
     }
 
     pub fn pop_back(&mut self) -> Option<T> {
-        if self.count == 0 {
+        if !self.spill.is_empty() {
+            self.spill.pop_back()
+        } else if self.count == 0 {
             None
         } else {
             if self.tail == 0 {
@@ -62,8 +71,8 @@ where
     }
 
     pub fn push_back(&mut self, v: T) {
-        if self.count == self.data.len() {
-            panic!("overfilled StackDequeue")
+        if self.count == self.data.len() || !self.spill.is_empty() {
+            self.spill.push_back(v)
         } else {
             let slot = unsafe { self.data.get_unchecked_mut(self.tail) };
             *slot = v;
@@ -95,7 +104,7 @@ where
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.pos >= self.q.count {
+        if self.pos >= self.q.len() {
             return None;
         }
         let old_pos = self.pos;
@@ -112,9 +121,13 @@ where
 
     fn index(&self, index: usize) -> &Self::Output {
         if index >= self.count {
+            if index < self.len() {
+                return &self.spill[index - self.count];
+            }
             panic!(
                 "index out of bounds: {} but stackdequeue length is only {}",
-                index, self.count
+                index,
+                self.len()
             );
         }
         let mut idx = self.head + index;
@@ -132,9 +145,13 @@ where
 {
     fn index_mut(&mut self, index: usize) -> &mut T {
         if index >= self.count {
+            if index < self.len() {
+                return &mut self.spill[index - self.count];
+            }
             panic!(
                 "index out of bounds: {} but stackdequeue length is only {}",
-                index, self.count
+                index,
+                self.len()
             );
         }
         let mut idx = self.head + index;
@@ -183,3 +200,30 @@ fn test_push_back_pop_back() {
 
     assert_eq!(q.pop_front(), None);
 }
+
+#[test]
+fn test_spill_to_heap() {
+    let mut q: StackDequeue<usize, 16> = StackDequeue::new();
+
+    for i in 0..40 {
+        q.push_back(i);
+    }
+    assert_eq!(q.len(), 40);
+    assert_eq!(q[20], 20);
+    assert_eq!(
+        q.iter().copied().collect::<Vec<_>>(),
+        (0..40).collect::<Vec<_>>()
+    );
+
+    // Room on the stack doesn't change the order of elements that spilled
+    for i in 0..10 {
+        assert_eq!(q.pop_front(), Some(i));
+    }
+    q.push_back(40);
+    assert_eq!(q.pop_back(), Some(40));
+    assert_eq!(q.pop_back(), Some(39));
+    for i in 10..39 {
+        assert_eq!(q.pop_front(), Some(i));
+    }
+    assert_eq!(q.pop_front(), None);
+}